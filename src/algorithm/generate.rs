@@ -0,0 +1,176 @@
+//! Generation of new keys, for example for tests or key rotation.
+//! `GeneratedKey` creates a key for an algorithm with a suggested key ID and
+//! export to PEM and JWK.
+//!
+//! HMAC secrets are always available and are read from the random number
+//! generator of the operating system. RSA, ECDSA and EdDSA keys are generated
+//! with OpenSSL if the `openssl` feature is enabled. Otherwise ES256, ES384
+//! and EdDSA keys are generated with ring if the `ring` feature is enabled,
+//! RSA keys require the `rsa` feature and other ECDSA keys the feature for
+//! the curve.
+//! ## Examples
+//! ```
+//! use jwt::{AlgorithmType, GeneratedKey, ToJwk};
+//! let key = GeneratedKey::generate(AlgorithmType::Hs256).unwrap();
+//! let jwk = key.to_private_jwk().unwrap();
+//! assert_eq!(jwk.key_id, Some(key.key_id));
+//! ```
+
+use rand_core::{OsRng, RngCore};
+
+use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+use crate::error::Error;
+use crate::jwk::{Jwk, KeyParameters, OctetSequenceParameters, ToJwk};
+
+/// A freshly generated key for one algorithm, with the signing and verifying
+/// keys ready to use and a suggested key ID. The key ID is the JWK thumbprint
+/// of the public key, or random for HMAC secrets, whose thumbprint would be
+/// derived from the secret.
+pub struct GeneratedKey {
+    pub key_id: String,
+    pub signing_key: Box<dyn SigningAlgorithm + Send + Sync>,
+    pub verifying_key: Box<dyn VerifyingAlgorithm + Send + Sync>,
+    algorithm: AlgorithmType,
+    private_jwk: Jwk,
+    public_key: Option<PublicKey>,
+}
+
+/// The exports of the public half of an asymmetric key, and the PEM encoding
+/// of its private key.
+struct PublicKey {
+    jwk: Jwk,
+    pem: Vec<u8>,
+    private_key_pem: Vec<u8>,
+}
+
+impl GeneratedKey {
+    /// Generate a key for `algorithm`. HMAC secrets are as long as the output
+    /// of the hash, RSA keys have 2048 bits and EdDSA keys use Ed25519.
+    /// Algorithms that can not be generated with the enabled features return
+    /// `Error::UnsupportedAlgorithm`.
+    pub fn generate(algorithm: AlgorithmType) -> Result<Self, Error> {
+        match algorithm {
+            AlgorithmType::Hs256 | AlgorithmType::Hs384 | AlgorithmType::Hs512 => {
+                GeneratedKey::generate_secret(algorithm)
+            }
+            _ => GeneratedKey::generate_key_pair(algorithm),
+        }
+    }
+
+    /// Generate an asymmetric key with OpenSSL if it is enabled, then with ring,
+    /// and with the RustCrypto crates for the algorithms neither supports.
+    fn generate_key_pair(algorithm: AlgorithmType) -> Result<Self, Error> {
+        #[cfg(feature = "openssl")]
+        let algorithm = match crate::algorithm::openssl::generate_key(algorithm) {
+            Err(Error::UnsupportedAlgorithm(algorithm)) => algorithm,
+            result => return result,
+        };
+        #[cfg(feature = "ring")]
+        let algorithm = match crate::algorithm::ring::generate_key(algorithm) {
+            Err(Error::UnsupportedAlgorithm(algorithm)) => algorithm,
+            result => return result,
+        };
+        crate::algorithm::rust_crypto::generate_key(algorithm)
+    }
+
+    fn generate_secret(algorithm: AlgorithmType) -> Result<Self, Error> {
+        let size = match algorithm {
+            AlgorithmType::Hs256 => 32,
+            AlgorithmType::Hs384 => 48,
+            _ => 64,
+        };
+        let mut secret = vec![0; size];
+        OsRng.try_fill_bytes(&mut secret)?;
+        let mut key_id = [0; 16];
+        OsRng.try_fill_bytes(&mut key_id)?;
+
+        let private_jwk = Jwk::signature_key(
+            KeyParameters::OctetSequence(OctetSequenceParameters { key_value: secret }),
+            algorithm.clone(),
+        );
+        Ok(GeneratedKey {
+            key_id: base64::encode_config(key_id, base64::URL_SAFE_NO_PAD),
+            signing_key: private_jwk.to_signing_key()?,
+            verifying_key: private_jwk.to_verifying_key()?,
+            algorithm,
+            private_jwk,
+            public_key: None,
+        })
+    }
+
+    /// Create a generated asymmetric key from the keys and exports of a
+    /// backend. The key ID is the thumbprint of `public_jwk`.
+    #[cfg(any(
+        feature = "openssl",
+        feature = "ring",
+        feature = "rsa",
+        feature = "p256",
+        feature = "p384",
+        feature = "p521",
+        feature = "k256"
+    ))]
+    pub(crate) fn from_key_pair(
+        signing_key: Box<dyn SigningAlgorithm + Send + Sync>,
+        verifying_key: Box<dyn VerifyingAlgorithm + Send + Sync>,
+        private_jwk: Jwk,
+        public_jwk: Jwk,
+        private_key_pem: Vec<u8>,
+        public_key_pem: Vec<u8>,
+    ) -> Self {
+        GeneratedKey {
+            key_id: public_jwk.thumbprint(),
+            algorithm: signing_key.algorithm_type(),
+            signing_key,
+            verifying_key,
+            private_jwk,
+            public_key: Some(PublicKey {
+                jwk: public_jwk,
+                pem: public_key_pem,
+                private_key_pem,
+            }),
+        }
+    }
+
+    /// The algorithm the key was generated for.
+    pub fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm.clone()
+    }
+
+    /// The private key as PEM encoded PKCS #8. HMAC secrets have no PEM
+    /// encoding and are exported with `to_private_jwk` instead.
+    pub fn private_key_to_pem(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.public_key()?.private_key_pem.clone())
+    }
+
+    /// The public key as a PEM encoded SubjectPublicKeyInfo.
+    pub fn public_key_to_pem(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.public_key()?.pem.clone())
+    }
+
+    /// The key including its private parameters as a JWK, with `kid`, `alg`
+    /// and `use` set. Keep this secret; use `to_jwk` to publish the key.
+    pub fn to_private_jwk(&self) -> Result<Jwk, Error> {
+        Ok(self.with_key_id(self.private_jwk.clone()))
+    }
+
+    fn public_key(&self) -> Result<&PublicKey, Error> {
+        self.public_key.as_ref().ok_or_else(|| {
+            Error::InvalidKey("symmetric keys have no public key or PEM encoding".to_owned())
+        })
+    }
+
+    fn with_key_id(&self, jwk: Jwk) -> Jwk {
+        Jwk {
+            key_id: Some(self.key_id.clone()),
+            ..jwk
+        }
+    }
+}
+
+impl ToJwk for GeneratedKey {
+    /// The public key as a JWK with `kid`, `alg` and `use` set. HMAC secrets
+    /// have no public part and return an error.
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        Ok(self.with_key_id(self.public_key()?.jwk.clone()))
+    }
+}
@@ -0,0 +1,456 @@
+//! A key ring that rotates signing keys on a schedule. Each key has an
+//! activation time and an optional retirement time. The ring signs with the
+//! most recently activated key that is not retired, and writes its key id
+//! into the header. Retired keys stay available for verification through
+//! `Store` for a grace period, so that tokens signed shortly before a
+//! rotation can still be verified. Signing through `Store`, for example with
+//! `SignWithStore`, only accepts keys that are active.
+//!
+//! Time is read through a `Clock`, which can be replaced to test rotation.
+//! With the `test-util` feature, `ManualClock` is a clock that only moves
+//! when it is told to.
+//! ```
+//! use hmac::{Hmac, Mac};
+//! use jwt::algorithm::key_ring::{Clock, KeyRing};
+//! use jwt::VerifyWithStore;
+//! use sha2::Sha256;
+//! use std::cell::Cell;
+//! use std::collections::BTreeMap;
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! struct DayClock(Cell<u32>);
+//!
+//! impl Clock for DayClock {
+//!     fn now(&self) -> SystemTime {
+//!         UNIX_EPOCH + self.0.get() * Duration::from_secs(24 * 60 * 60)
+//!     }
+//! }
+//!
+//! # use jwt::Error;
+//! # fn try_main() -> Result<(), Error> {
+//! let day = Duration::from_secs(24 * 60 * 60);
+//! let clock = DayClock(Cell::new(100));
+//! let mut key_ring = KeyRing::with_clock(&clock).with_grace_period(day);
+//! let old_key: Hmac<Sha256> = Hmac::new_from_slice(b"old-secret")?;
+//! let new_key: Hmac<Sha256> = Hmac::new_from_slice(b"new-secret")?;
+//! key_ring.insert("old", old_key, UNIX_EPOCH, Some(UNIX_EPOCH + 101 * day));
+//! key_ring.insert("new", new_key, UNIX_EPOCH + 101 * day, None);
+//!
+//! let mut claims = BTreeMap::new();
+//! claims.insert("sub", "someone");
+//! let old_token = key_ring.sign(&claims)?;
+//! assert_eq!(key_ring.active_key()?.0, "old");
+//!
+//! clock.0.set(101);
+//! assert_eq!(key_ring.active_key()?.0, "new");
+//! let claims: BTreeMap<String, String> = old_token.as_str().verify_with_store(&key_ring)?;
+//! assert_eq!(claims["sub"], "someone");
+//! # Ok(())
+//! # }
+//! # try_main().unwrap()
+//! ```
+
+use std::sync::Arc;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::algorithm::store::{Candidates, Store};
+use crate::algorithm::SigningAlgorithm;
+use crate::error::Error;
+use crate::header::{BorrowedKeyHeader, Header};
+use crate::token::signed::SignWithKey;
+use crate::token::{Signed, Unsigned};
+use crate::{ToBase64, Token};
+
+/// A source of the current time.
+pub trait Clock {
+    fn now(&self) -> SystemTime;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// The system clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when it is told to, for tests. Available with the
+/// `test-util` feature.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    pub fn new(now: SystemTime) -> Self {
+        ManualClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.lock() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SystemTime> {
+        self.now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.lock()
+    }
+}
+
+/// Signing keys with activation and retirement times. See the [module
+/// documentation](index.html) for an example.
+pub struct KeyRing<A, C = SystemClock> {
+    keys: Vec<RingKey<A>>,
+    grace_period: Duration,
+    clock: C,
+}
+
+struct RingKey<A> {
+    key_id: String,
+    key: A,
+    activates_at: SystemTime,
+    retires_at: Option<SystemTime>,
+}
+
+impl<A> KeyRing<A> {
+    /// An empty key ring that reads the system clock.
+    pub fn new() -> Self {
+        KeyRing::with_clock(SystemClock)
+    }
+}
+
+impl<A> Default for KeyRing<A> {
+    fn default() -> Self {
+        KeyRing::new()
+    }
+}
+
+impl<A, C: Clock> KeyRing<A, C> {
+    /// An empty key ring that reads `clock`, without a grace period.
+    pub fn with_clock(clock: C) -> Self {
+        KeyRing {
+            keys: Vec::new(),
+            grace_period: Duration::from_secs(0),
+            clock,
+        }
+    }
+
+    /// How long retired keys can still be used for verification.
+    pub fn with_grace_period(self, grace_period: Duration) -> Self {
+        KeyRing {
+            grace_period,
+            ..self
+        }
+    }
+
+    /// Add a key that is used for signing from `activates_at` until
+    /// `retires_at`, replacing any key with the same key id. Keys can be
+    /// added before they are activated, so that verifiers can learn about
+    /// them ahead of the rotation.
+    pub fn insert(
+        &mut self,
+        key_id: impl Into<String>,
+        key: A,
+        activates_at: SystemTime,
+        retires_at: Option<SystemTime>,
+    ) {
+        let key_id = key_id.into();
+        self.keys.retain(|ring_key| ring_key.key_id != key_id);
+        self.keys.push(RingKey {
+            key_id,
+            key,
+            activates_at,
+            retires_at,
+        });
+    }
+
+    /// Retire the key with `key_id` at `retires_at`.
+    pub fn retire(&mut self, key_id: &str, retires_at: SystemTime) -> Result<(), Error> {
+        let ring_key = self
+            .keys
+            .iter_mut()
+            .find(|ring_key| ring_key.key_id == key_id)
+            .ok_or_else(|| Error::NoKeyWithKeyId(key_id.to_owned()))?;
+        ring_key.retires_at = Some(retires_at);
+        Ok(())
+    }
+
+    /// Remove the keys whose grace period has ended.
+    pub fn remove_expired(&mut self) {
+        let now = self.clock.now();
+        let grace_period = self.grace_period;
+        self.keys
+            .retain(|ring_key| !ring_key.is_expired(now, grace_period));
+    }
+
+    /// The key id and key used for signing now: the most recently activated
+    /// key that is not retired.
+    pub fn active_key(&self) -> Result<(&str, &A), Error> {
+        let now = self.clock.now();
+        self.keys
+            .iter()
+            .filter(|ring_key| ring_key.is_active(now))
+            .max_by_key(|ring_key| ring_key.activates_at)
+            .map(|ring_key| (ring_key.key_id.as_str(), &ring_key.key))
+            .ok_or(Error::NoActiveKey)
+    }
+
+    /// The keys that can be used for verification now, including keys that
+    /// are not active yet and retired keys within the grace period. These are
+    /// the keys to publish, for example with `JwkSet::from_public_keys`.
+    pub fn verifying_keys(&self) -> impl Iterator<Item = (&String, &A)> {
+        let now = self.clock.now();
+        let grace_period = self.grace_period;
+        self.keys
+            .iter()
+            .filter(move |ring_key| !ring_key.is_expired(now, grace_period))
+            .map(|ring_key| (&ring_key.key_id, &ring_key.key))
+    }
+
+    /// Sign `claims` with the active key and its key id.
+    pub fn sign<T: ToBase64>(&self, claims: T) -> Result<String, Error>
+    where
+        A: SigningAlgorithm,
+    {
+        let (key_id, key) = self.active_key()?;
+        let header = BorrowedKeyHeader {
+            algorithm: key.algorithm_type(),
+            key_id,
+        };
+        Ok(Token::new(header, claims).sign_with_key(key)?.into())
+    }
+
+    /// Sign a token with the active key, setting the algorithm and key id of
+    /// the header to those of the key.
+    pub fn sign_token<T: ToBase64>(
+        &self,
+        mut token: Token<Header, T, Unsigned>,
+    ) -> Result<Token<Header, T, Signed>, Error>
+    where
+        A: SigningAlgorithm,
+    {
+        let (key_id, key) = self.active_key()?;
+        let header = token.header_mut();
+        header.algorithm = key.algorithm_type();
+        header.key_id = Some(key_id.to_owned());
+        token.sign_with_key(key)
+    }
+}
+
+impl<A> RingKey<A> {
+    fn is_active(&self, now: SystemTime) -> bool {
+        self.activates_at <= now && self.retires_at.map_or(true, |retires_at| now < retires_at)
+    }
+
+    fn is_expired(&self, now: SystemTime, grace_period: Duration) -> bool {
+        self.retires_at
+            .map_or(false, |retires_at| now >= retires_at + grace_period)
+    }
+}
+
+impl<A, C: Clock> Store for KeyRing<A, C> {
+    type Algorithm = A;
+
+    /// The key with `key_id`, unless its grace period has ended.
+    fn get(&self, key_id: &str) -> Option<&A> {
+        let now = self.clock.now();
+        self.keys
+            .iter()
+            .find(|ring_key| ring_key.key_id == key_id)
+            .filter(|ring_key| !ring_key.is_expired(now, self.grace_period))
+            .map(|ring_key| &ring_key.key)
+    }
+
+    /// The key with `key_id` if it is active, so that tokens can only be
+    /// signed with keys between their activation and retirement.
+    fn signing_key(&self, key_id: &str) -> Result<&A, Error> {
+        let now = self.clock.now();
+        let ring_key = self
+            .keys
+            .iter()
+            .find(|ring_key| ring_key.key_id == key_id)
+            .ok_or_else(|| Error::NoKeyWithKeyId(key_id.to_owned()))?;
+        if now < ring_key.activates_at {
+            return Err(Error::KeyNotYetValid);
+        }
+        if !ring_key.is_active(now) {
+            return Err(Error::KeyExpired);
+        }
+        Ok(&ring_key.key)
+    }
+
+    fn candidates(&self) -> Candidates<'_, A> {
+        Box::new(
+            self.verifying_keys()
+                .map(|(key_id, key)| (Some(key_id.as_str()), key)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use crate::algorithm::key_ring::{KeyRing, ManualClock};
+    use crate::algorithm::store::Store;
+    use crate::algorithm::AlgorithmType;
+    use crate::error::Error;
+    use crate::header::Header;
+    use crate::token::signed::SignWithStore;
+    use crate::token::verified::VerifyWithStore;
+    use crate::Token;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn at(hours: u32) -> SystemTime {
+        UNIX_EPOCH + HOUR * hours
+    }
+
+    fn key(secret: &str) -> Result<Hmac<Sha256>, Error> {
+        Ok(Hmac::new_from_slice(secret.as_bytes())?)
+    }
+
+    #[test]
+    fn rotation() -> Result<(), Error> {
+        let clock = ManualClock::new(at(0));
+        let mut key_ring = KeyRing::with_clock(&clock).with_grace_period(HOUR);
+        key_ring.insert("first", key("first")?, at(0), Some(at(10)));
+        key_ring.insert("second", key("second")?, at(10), None);
+
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        let first_token = key_ring.sign(&claims)?;
+        let token: Token<Header, BTreeMap<String, String>, _> =
+            first_token.as_str().verify_with_store(&key_ring)?;
+        assert_eq!(token.header().key_id.as_deref(), Some("first"));
+        assert_eq!(key_ring.verifying_keys().count(), 2);
+
+        clock.set(at(10));
+        let second_token = key_ring.sign(&claims)?;
+        let token: Token<Header, BTreeMap<String, String>, _> =
+            second_token.as_str().verify_with_store(&key_ring)?;
+        assert_eq!(token.header().key_id.as_deref(), Some("second"));
+        let _: BTreeMap<String, String> = first_token.as_str().verify_with_store(&key_ring)?;
+
+        clock.advance(HOUR);
+        let result: Result<BTreeMap<String, String>, _> =
+            first_token.as_str().verify_with_store(&key_ring);
+        match result {
+            Err(Error::NoKeyWithKeyId(key_id)) => assert_eq!(key_id, "first"),
+            other => panic!("Wrong result: {:?}", other),
+        }
+        let names: Vec<_> = key_ring
+            .verifying_keys()
+            .map(|(key_id, _)| key_id)
+            .collect();
+        assert_eq!(names, ["second"]);
+
+        key_ring.remove_expired();
+        key_ring.retire("second", at(12))?;
+        clock.set(at(12));
+        match key_ring.sign(&claims) {
+            Err(Error::NoActiveKey) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn newest_active_key_signs() -> Result<(), Error> {
+        let clock = ManualClock::new(at(5));
+        let mut key_ring = KeyRing::with_clock(&clock);
+        key_ring.insert("old", key("old")?, at(0), None);
+        key_ring.insert("new", key("new")?, at(4), None);
+        key_ring.insert("future", key("future")?, at(6), None);
+        assert_eq!(key_ring.active_key()?.0, "new");
+        assert!(key_ring.get("future").is_some());
+
+        key_ring.insert("new", key("replaced")?, at(1), None);
+        assert_eq!(key_ring.active_key()?.0, "new");
+        assert_eq!(key_ring.verifying_keys().count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn store_signs_only_with_active_keys() -> Result<(), Error> {
+        let clock = ManualClock::new(at(5));
+        let mut key_ring = KeyRing::with_clock(&clock).with_grace_period(HOUR);
+        key_ring.insert("retired", key("retired")?, at(0), Some(at(5)));
+        key_ring.insert("current", key("current")?, at(5), None);
+        key_ring.insert("future", key("future")?, at(6), None);
+
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        ("current", &claims).sign_with_store(&key_ring)?;
+        match ("retired", &claims).sign_with_store(&key_ring) {
+            Err(Error::KeyExpired) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+        match ("future", &claims).sign_with_store(&key_ring) {
+            Err(Error::KeyNotYetValid) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        // Both keys can still verify tokens.
+        assert!(key_ring.get("retired").is_some());
+        assert!(key_ring.get("future").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn sign_token_sets_header() -> Result<(), Error> {
+        let clock = ManualClock::new(at(1));
+        let mut key_ring = KeyRing::with_clock(&clock);
+        key_ring.insert("current", key("current")?, at(0), None);
+
+        let header = Header {
+            algorithm: AlgorithmType::Hs512,
+            key_id: Some("stale".to_owned()),
+            ..Default::default()
+        };
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        let token = key_ring.sign_token(Token::new(header, claims))?;
+        assert_eq!(token.header().algorithm, AlgorithmType::Hs256);
+        assert_eq!(token.header().key_id.as_deref(), Some("current"));
+
+        let verified: Token<Header, BTreeMap<String, String>, _> =
+            token.as_str().verify_with_store(&key_ring)?;
+        assert_eq!(verified.claims()["sub"], "someone");
+        Ok(())
+    }
+}
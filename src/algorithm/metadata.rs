@@ -0,0 +1,380 @@
+//! Restrictions on when and how a key may be used. A `RestrictedKey` pairs a
+//! key with its `KeyMetadata` and can be used, or kept in a `Store`, wherever
+//! the key itself could. Signing and verifying tokens check the restrictions
+//! before the signature, and fail with a specific `Error` for each kind of
+//! violation. The validity times are checked against the system clock, or the
+//! `Clock` set in the metadata.
+//! ```
+//! use hmac::{Hmac, Mac};
+//! use jwt::algorithm::metadata::{KeyMetadata, RestrictedKey};
+//! use jwt::{Error, SignWithStore};
+//! use sha2::Sha256;
+//! use std::collections::BTreeMap;
+//!
+//! let key: Hmac<Sha256> = Hmac::new_from_slice(b"some-secret").unwrap();
+//! let metadata = KeyMetadata {
+//!     issuers: Some(vec!["https://issuer.example".to_owned()]),
+//!     ..Default::default()
+//! };
+//! let mut store = BTreeMap::new();
+//! store.insert("restricted", RestrictedKey::new(key, metadata));
+//!
+//! let mut claims = BTreeMap::new();
+//! claims.insert("iss", "https://other.example");
+//! match ("restricted", claims).sign_with_store(&store) {
+//!     Err(Error::IssuerNotAllowed(issuer)) => {
+//!         assert_eq!(issuer.as_deref(), Some("https://other.example"))
+//!     }
+//!     other => panic!("Wrong result: {:?}", other),
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::algorithm::key_ring::Clock;
+use crate::algorithm::{
+    AlgorithmType, AsyncSigningAlgorithm, SignatureFuture, SigningAlgorithm, VerifyingAlgorithm,
+};
+use crate::error::Error;
+use crate::jwk::{KeyOperation, KeyUse};
+use crate::FromBase64;
+
+/// When and how a key may be used. Every restriction is optional, and
+/// unset restrictions allow anything.
+#[derive(Clone, Default)]
+pub struct KeyMetadata {
+    /// The key may not be used before this time.
+    pub not_before: Option<SystemTime>,
+    /// The key may not be used at or after this time.
+    pub not_after: Option<SystemTime>,
+    /// The key may only be used for signatures if this is
+    /// `KeyUse::Signature`, and not at all otherwise.
+    pub key_use: Option<KeyUse>,
+    /// The operations the key may be used for, `KeyOperation::Sign` and
+    /// `KeyOperation::Verify`.
+    pub key_operations: Option<Vec<KeyOperation>>,
+    /// The algorithms the key may be used with.
+    pub algorithms: Option<Vec<AlgorithmType>>,
+    /// The `iss` claims of the tokens that the key may sign or verify. Tokens
+    /// without an issuer are rejected.
+    pub issuers: Option<Vec<String>>,
+    /// The clock that `not_before` and `not_after` are checked against, the
+    /// system clock if unset.
+    pub clock: Option<Arc<dyn Clock + Send + Sync>>,
+}
+
+impl fmt::Debug for KeyMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyMetadata")
+            .field("not_before", &self.not_before)
+            .field("not_after", &self.not_after)
+            .field("key_use", &self.key_use)
+            .field("key_operations", &self.key_operations)
+            .field("algorithms", &self.algorithms)
+            .field("issuers", &self.issuers)
+            .field("clock", &self.clock.as_ref().map(|clock| clock.now()))
+            .finish()
+    }
+}
+
+/// The only claim needed for the checks, so that other claims do not have to
+/// be valid registered claims.
+#[derive(Deserialize)]
+struct IssuerClaim {
+    #[serde(rename = "iss")]
+    issuer: Option<String>,
+}
+
+impl KeyMetadata {
+    /// Check that the key may perform `operation` with `algorithm` now, on a
+    /// token with the base64 encoded `claims`.
+    pub(crate) fn check(
+        &self,
+        operation: KeyOperation,
+        algorithm: &AlgorithmType,
+        claims: &str,
+    ) -> Result<(), Error> {
+        let now = self
+            .clock
+            .as_ref()
+            .map_or_else(SystemTime::now, |clock| clock.now());
+        if self.not_before.map_or(false, |not_before| now < not_before) {
+            return Err(Error::KeyNotYetValid);
+        }
+        if self.not_after.map_or(false, |not_after| now >= not_after) {
+            return Err(Error::KeyExpired);
+        }
+
+        let use_allowed = self
+            .key_use
+            .as_ref()
+            .map_or(true, |key_use| *key_use == KeyUse::Signature);
+        let operation_allowed = self
+            .key_operations
+            .as_ref()
+            .map_or(true, |operations| operations.contains(&operation));
+        if !use_allowed || !operation_allowed {
+            return Err(Error::KeyOperationNotAllowed(operation));
+        }
+
+        if let Some(ref algorithms) = self.algorithms {
+            if !algorithms.contains(algorithm) {
+                return Err(Error::AlgorithmNotAllowed(algorithm.clone()));
+            }
+        }
+
+        if let Some(ref issuers) = self.issuers {
+            let issuer = IssuerClaim::from_base64(claims)?.issuer;
+            if !issuer
+                .as_ref()
+                .map_or(false, |issuer| issuers.contains(issuer))
+            {
+                return Err(Error::IssuerNotAllowed(issuer));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A key together with the restrictions on its use.
+pub struct RestrictedKey<A> {
+    pub key: A,
+    pub metadata: KeyMetadata,
+}
+
+impl<A> RestrictedKey<A> {
+    pub fn new(key: A, metadata: KeyMetadata) -> Self {
+        RestrictedKey { key, metadata }
+    }
+}
+
+impl<A: SigningAlgorithm> SigningAlgorithm for RestrictedKey<A> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.key.algorithm_type()
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        self.key.sign(header, claims)
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.key.sign_bytes(header, claims)
+    }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        Some(&self.metadata)
+    }
+}
+
+impl<A: VerifyingAlgorithm> VerifyingAlgorithm for RestrictedKey<A> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.key.algorithm_type()
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        self.key.verify_bytes(header, claims, signature)
+    }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        Some(&self.metadata)
+    }
+}
+
+impl<A: AsyncSigningAlgorithm> AsyncSigningAlgorithm for RestrictedKey<A> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.key.algorithm_type()
+    }
+
+    fn sign<'a>(&'a self, signing_input: &'a [u8]) -> SignatureFuture<'a> {
+        self.key.sign(signing_input)
+    }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        Some(&self.metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use crate::algorithm::key_ring::ManualClock;
+    use crate::algorithm::metadata::{KeyMetadata, RestrictedKey};
+    use crate::algorithm::AlgorithmType;
+    use crate::error::Error;
+    use crate::jwk::{KeyOperation, KeyUse};
+    use crate::token::signed::{SignWithKey, SignWithStore};
+    use crate::token::verified::VerifyWithStore;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn restricted(
+        metadata: KeyMetadata,
+    ) -> Result<BTreeMap<&'static str, RestrictedKey<Hmac<Sha256>>>, Error> {
+        let mut store = BTreeMap::new();
+        store.insert(
+            "key",
+            RestrictedKey::new(Hmac::new_from_slice(b"secret")?, metadata),
+        );
+        Ok(store)
+    }
+
+    fn claims(issuer: &str) -> BTreeMap<&str, &str> {
+        let mut claims = BTreeMap::new();
+        claims.insert("iss", issuer);
+        claims
+    }
+
+    fn sign(metadata: KeyMetadata) -> Result<String, Error> {
+        ("key", claims("issuer")).sign_with_store(&restricted(metadata)?)
+    }
+
+    fn verify(metadata: KeyMetadata) -> Result<(), Error> {
+        let unrestricted = restricted(KeyMetadata::default())?;
+        let token = ("key", claims("issuer")).sign_with_store(&unrestricted)?;
+        let _: BTreeMap<String, String> =
+            token.as_str().verify_with_store(&restricted(metadata)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn unrestricted() -> Result<(), Error> {
+        sign(KeyMetadata::default())?;
+        verify(KeyMetadata::default())
+    }
+
+    #[test]
+    fn validity_window() -> Result<(), Error> {
+        let now = SystemTime::now();
+        let current = KeyMetadata {
+            not_before: Some(now - DAY),
+            not_after: Some(now + DAY),
+            ..Default::default()
+        };
+        sign(current.clone())?;
+        verify(current)?;
+
+        let future = KeyMetadata {
+            not_before: Some(now + DAY),
+            ..Default::default()
+        };
+        assert!(matches!(sign(future.clone()), Err(Error::KeyNotYetValid)));
+        assert!(matches!(verify(future), Err(Error::KeyNotYetValid)));
+
+        let expired = KeyMetadata {
+            not_after: Some(now - DAY),
+            ..Default::default()
+        };
+        assert!(matches!(sign(expired.clone()), Err(Error::KeyExpired)));
+        assert!(matches!(verify(expired), Err(Error::KeyExpired)));
+        Ok(())
+    }
+
+    #[test]
+    fn validity_boundaries() -> Result<(), Error> {
+        let start = UNIX_EPOCH + 100 * DAY;
+        let end = start + DAY;
+        let clock = Arc::new(ManualClock::new(start - Duration::from_nanos(1)));
+        let metadata = KeyMetadata {
+            not_before: Some(start),
+            not_after: Some(end),
+            clock: Some(clock.clone()),
+            ..Default::default()
+        };
+        match verify(metadata.clone()) {
+            Err(Error::KeyNotYetValid) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        clock.set(start);
+        sign(metadata.clone())?;
+        verify(metadata.clone())?;
+
+        clock.set(end - Duration::from_nanos(1));
+        sign(metadata.clone())?;
+
+        clock.set(end);
+        match sign(metadata) {
+            Err(Error::KeyExpired) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn operations() -> Result<(), Error> {
+        let verify_only = KeyMetadata {
+            key_use: Some(KeyUse::Signature),
+            key_operations: Some(vec![KeyOperation::Verify]),
+            ..Default::default()
+        };
+        verify(verify_only.clone())?;
+        match sign(verify_only) {
+            Err(Error::KeyOperationNotAllowed(KeyOperation::Sign)) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let encryption = KeyMetadata {
+            key_use: Some(KeyUse::Encryption),
+            ..Default::default()
+        };
+        match verify(encryption) {
+            Err(Error::KeyOperationNotAllowed(KeyOperation::Verify)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn algorithms() -> Result<(), Error> {
+        let hs256 = KeyMetadata {
+            algorithms: Some(vec![AlgorithmType::Hs256]),
+            ..Default::default()
+        };
+        sign(hs256.clone())?;
+        verify(hs256)?;
+
+        let hs512 = KeyMetadata {
+            algorithms: Some(vec![AlgorithmType::Hs512]),
+            ..Default::default()
+        };
+        match sign(hs512) {
+            Err(Error::AlgorithmNotAllowed(AlgorithmType::Hs256)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn issuers() -> Result<(), Error> {
+        let metadata = KeyMetadata {
+            issuers: Some(vec!["issuer".to_owned()]),
+            ..Default::default()
+        };
+        sign(metadata.clone())?;
+        verify(metadata.clone())?;
+
+        let other = KeyMetadata {
+            issuers: Some(vec!["other".to_owned()]),
+            ..Default::default()
+        };
+        match verify(other) {
+            Err(Error::IssuerNotAllowed(Some(issuer))) => assert_eq!(issuer, "issuer"),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let key = RestrictedKey::new(Hmac::<Sha256>::new_from_slice(b"secret")?, metadata);
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        match claims.sign_with_key(&key) {
+            Err(Error::IssuerNotAllowed(None)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+}
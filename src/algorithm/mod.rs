@@ -1,7 +1,11 @@
 //! Algorithms capable of signing and verifying tokens. By default only the
 //! `hmac` crate's `Hmac` type is supported. For more algorithms, enable the
 //! feature `openssl` and see the [openssl](openssl/index.html)
-//! module. The `none` algorithm is explicitly not supported.
+//! module, or enable the features `rsa`, `p256`, `p384`, `p521` and `k256` for pure
+//! Rust RSA and ECDSA support in the [rust_crypto](rust_crypto/index.html)
+//! module. The feature `ring` enables the [ring](ring/index.html) module. The `none` algorithm is
+//! not supported by any key, see the [unsecured](../token/unsecured/index.html)
+//! module for opting in to unsecured tokens.
 //! ## Examples
 //! ```
 //! use hmac::{Hmac, Mac};
@@ -9,21 +13,64 @@
 //!
 //! let hs256_key: Hmac<Sha256> = Hmac::new_from_slice(b"some-secret").unwrap();
 //! ```
+//!
+//! Algorithms that are not part of the JWA registry can be plugged in by
+//! implementing `SigningAlgorithm` and `VerifyingAlgorithm` with an
+//! `AlgorithmType::Other` name. Tokens with that `alg` are then routed to
+//! the custom implementation, while other keys reject them with
+//! `Error::AlgorithmMismatch`.
+//! ```
+//! use jwt::{AlgorithmType, Error, SigningAlgorithm, VerifyingAlgorithm};
+//!
+//! struct PrivateAlgorithm;
+//!
+//! impl SigningAlgorithm for PrivateAlgorithm {
+//!     fn algorithm_type(&self) -> AlgorithmType {
+//!         AlgorithmType::Other("X-PRIVATE".to_owned())
+//!     }
+//!
+//!     fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+//!         // Compute the base64 encoded signature over `header.claims` here
+//! #       Ok(String::new())
+//!     }
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::algorithm::metadata::KeyMetadata;
 use crate::error::Error;
 
+pub mod generate;
+pub mod key_ring;
+pub mod metadata;
 #[cfg(feature = "openssl")]
 pub mod openssl;
+#[cfg(feature = "ring")]
+pub mod ring;
 pub mod rust_crypto;
 pub mod store;
 
 /// The type of an algorithm, corresponding to the
-/// [JWA](https://tools.ietf.org/html/rfc7518) specification.
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "UPPERCASE")]
+/// [JWA](https://tools.ietf.org/html/rfc7518) specification. Algorithm names
+/// that are not registered there are kept as `Other`, so that tokens using
+/// them can still be parsed and then routed or rejected.
+///
+/// Names are case sensitive. `parse` and deserialization always produce the
+/// dedicated variant for a registered name, so `Other` should only be used
+/// for names that do not have one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub enum AlgorithmType {
+    #[default]
     Hs256,
     Hs384,
     Hs512,
@@ -33,25 +80,108 @@ pub enum AlgorithmType {
     Es256,
     Es384,
     Es512,
+    Es256k,
     Ps256,
     Ps384,
     Ps512,
-    #[serde(rename = "none")]
+    EdDsa,
     None,
+    Other(String),
 }
 
-impl Default for AlgorithmType {
-    fn default() -> Self {
-        AlgorithmType::Hs256
+impl AlgorithmType {
+    /// The name of the algorithm as used in the `alg` header parameter.
+    pub fn name(&self) -> &str {
+        match self {
+            AlgorithmType::Hs256 => "HS256",
+            AlgorithmType::Hs384 => "HS384",
+            AlgorithmType::Hs512 => "HS512",
+            AlgorithmType::Rs256 => "RS256",
+            AlgorithmType::Rs384 => "RS384",
+            AlgorithmType::Rs512 => "RS512",
+            AlgorithmType::Es256 => "ES256",
+            AlgorithmType::Es384 => "ES384",
+            AlgorithmType::Es512 => "ES512",
+            AlgorithmType::Es256k => "ES256K",
+            AlgorithmType::Ps256 => "PS256",
+            AlgorithmType::Ps384 => "PS384",
+            AlgorithmType::Ps512 => "PS512",
+            AlgorithmType::EdDsa => "EdDSA",
+            AlgorithmType::None => "none",
+            AlgorithmType::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for AlgorithmType {
+    fn from(name: &str) -> Self {
+        match name {
+            "HS256" => AlgorithmType::Hs256,
+            "HS384" => AlgorithmType::Hs384,
+            "HS512" => AlgorithmType::Hs512,
+            "RS256" => AlgorithmType::Rs256,
+            "RS384" => AlgorithmType::Rs384,
+            "RS512" => AlgorithmType::Rs512,
+            "ES256" => AlgorithmType::Es256,
+            "ES384" => AlgorithmType::Es384,
+            "ES512" => AlgorithmType::Es512,
+            "ES256K" => AlgorithmType::Es256k,
+            "PS256" => AlgorithmType::Ps256,
+            "PS384" => AlgorithmType::Ps384,
+            "PS512" => AlgorithmType::Ps512,
+            "EdDSA" => AlgorithmType::EdDsa,
+            "none" => AlgorithmType::None,
+            other => AlgorithmType::Other(other.to_owned()),
+        }
+    }
+}
+
+impl FromStr for AlgorithmType {
+    type Err = Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(AlgorithmType::from(name))
+    }
+}
+
+impl fmt::Display for AlgorithmType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for AlgorithmType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for AlgorithmType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = Cow::<str>::deserialize(deserializer)?;
+        Ok(AlgorithmType::from(&*name))
     }
 }
 
 /// An algorithm capable of signing base64 encoded header and claims strings.
-/// strings.
 pub trait SigningAlgorithm {
     fn algorithm_type(&self) -> AlgorithmType;
 
     fn sign(&self, header: &str, claims: &str) -> Result<String, Error>;
+
+    /// Sign the header and claims, returning the raw signature bytes. By
+    /// default this decodes the signature returned by `sign`, implementations
+    /// that produce the raw bytes should override it to skip the round trip.
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let signature = self.sign(header, claims)?;
+        Ok(base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?)
+    }
+
+    /// Restrictions on the use of the key, checked before signing. Keys are
+    /// unrestricted by default, see [metadata](metadata/index.html).
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        None
+    }
 }
 
 /// An algorithm capable of verifying base64 encoded header and claims strings.
@@ -62,7 +192,124 @@ pub trait VerifyingAlgorithm {
 
     fn verify(&self, header: &str, claims: &str, signature: &str) -> Result<bool, Error> {
         let signature_bytes = base64::decode_config(signature, base64::URL_SAFE_NO_PAD)?;
-        self.verify_bytes(header, claims, &*signature_bytes)
+        self.verify_bytes(header, claims, &signature_bytes)
+    }
+
+    /// Restrictions on the use of the key, checked before verifying. Keys are
+    /// unrestricted by default, see [metadata](metadata/index.html).
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        None
+    }
+}
+
+/// The future returned by `AsyncSigningAlgorithm::sign`.
+pub type SignatureFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>, Error>> + Send + 'a>>;
+
+/// An algorithm that signs asynchronously, for example by calling a remote
+/// key management service or HSM that holds the key material. The signer
+/// receives the signing input, which is the base64 encoded header and claims
+/// joined by a `.`, and returns the raw signature bytes.
+/// ## Examples
+/// ```
+/// use jwt::algorithm::{AsyncSigningAlgorithm, SignatureFuture};
+/// use jwt::AlgorithmType;
+///
+/// struct RemoteSigner;
+///
+/// impl AsyncSigningAlgorithm for RemoteSigner {
+///     fn algorithm_type(&self) -> AlgorithmType {
+///         AlgorithmType::Es256
+///     }
+///
+///     fn sign<'a>(&'a self, signing_input: &'a [u8]) -> SignatureFuture<'a> {
+///         Box::pin(async move {
+///             // Send the signing input to the remote signer here
+/// #           Ok(signing_input.to_vec())
+///         })
+///     }
+/// }
+/// ```
+pub trait AsyncSigningAlgorithm {
+    fn algorithm_type(&self) -> AlgorithmType;
+
+    fn sign<'a>(&'a self, signing_input: &'a [u8]) -> SignatureFuture<'a>;
+
+    /// Restrictions on the use of the key, checked before signing. Keys are
+    /// unrestricted by default, see [metadata](metadata/index.html).
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        None
+    }
+}
+
+/// A signing key together with its verifying key, so that a single value can
+/// both sign and verify tokens and be kept in a single `Store`. Backends can
+/// provide constructors that derive the verifying key from the signing key,
+/// see for example `KeyPair::from_private_key` in the
+/// [openssl](openssl/index.html) module.
+pub struct KeyPair<S, V> {
+    signing_key: S,
+    verifying_key: V,
+}
+
+impl<S: SigningAlgorithm, V: VerifyingAlgorithm> KeyPair<S, V> {
+    /// Pair the two keys, checking that they use the same algorithm. The keys
+    /// themselves are not checked to match.
+    pub fn new(signing_key: S, verifying_key: V) -> Result<Self, Error> {
+        let signing_algorithm = signing_key.algorithm_type();
+        let verifying_algorithm = verifying_key.algorithm_type();
+        if signing_algorithm != verifying_algorithm {
+            return Err(Error::AlgorithmMismatch(
+                signing_algorithm,
+                verifying_algorithm,
+            ));
+        }
+
+        Ok(KeyPair {
+            signing_key,
+            verifying_key,
+        })
+    }
+}
+
+impl<S, V> KeyPair<S, V> {
+    pub fn signing_key(&self) -> &S {
+        &self.signing_key
+    }
+
+    pub fn verifying_key(&self) -> &V {
+        &self.verifying_key
+    }
+}
+
+impl<S: SigningAlgorithm, V> SigningAlgorithm for KeyPair<S, V> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.signing_key.algorithm_type()
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.signing_key.sign_bytes(header, claims)
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        self.signing_key.sign(header, claims)
+    }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        self.signing_key.key_metadata()
+    }
+}
+
+impl<S, V: VerifyingAlgorithm> VerifyingAlgorithm for KeyPair<S, V> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.verifying_key.algorithm_type()
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        self.verifying_key.verify_bytes(header, claims, signature)
+    }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        self.verifying_key.key_metadata()
     }
 }
 
@@ -75,6 +322,10 @@ impl<T: AsRef<dyn VerifyingAlgorithm>> VerifyingAlgorithm for T {
     fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
         self.as_ref().verify_bytes(header, claims, signature)
     }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        self.as_ref().key_metadata()
+    }
 }
 
 impl<T: AsRef<dyn SigningAlgorithm>> SigningAlgorithm for T {
@@ -82,7 +333,150 @@ impl<T: AsRef<dyn SigningAlgorithm>> SigningAlgorithm for T {
         self.as_ref().algorithm_type()
     }
 
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.as_ref().sign_bytes(header, claims)
+    }
+
     fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
         self.as_ref().sign(header, claims)
     }
+
+    fn key_metadata(&self) -> Option<&KeyMetadata> {
+        self.as_ref().key_metadata()
+    }
+}
+
+/// Thread safe boxed keys, as returned by the key loaders and used by
+/// `JwkSet`, are algorithms through the `AsRef` impls above.
+impl AsRef<dyn VerifyingAlgorithm> for Box<dyn VerifyingAlgorithm + Send + Sync> {
+    fn as_ref(&self) -> &(dyn VerifyingAlgorithm + 'static) {
+        &**self
+    }
+}
+
+impl AsRef<dyn SigningAlgorithm> for Box<dyn SigningAlgorithm + Send + Sync> {
+    fn as_ref(&self) -> &(dyn SigningAlgorithm + 'static) {
+        &**self
+    }
+}
+
+macro_rules! smart_pointer_async_algorithm {
+    ($pointer: ident) => {
+        impl<T: AsyncSigningAlgorithm + ?Sized> AsyncSigningAlgorithm for $pointer<T> {
+            fn algorithm_type(&self) -> AlgorithmType {
+                (**self).algorithm_type()
+            }
+
+            fn sign<'a>(&'a self, signing_input: &'a [u8]) -> SignatureFuture<'a> {
+                (**self).sign(signing_input)
+            }
+
+            fn key_metadata(&self) -> Option<&KeyMetadata> {
+                (**self).key_metadata()
+            }
+        }
+    };
+}
+
+smart_pointer_async_algorithm!(Box);
+smart_pointer_async_algorithm!(Rc);
+smart_pointer_async_algorithm!(Arc);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use hmac::{Hmac, Mac};
+    use sha2::{Sha256, Sha512};
+
+    use crate::algorithm::{AlgorithmType, KeyPair, SigningAlgorithm, VerifyingAlgorithm};
+    use crate::error::Error;
+    use crate::header::{Header, JoseHeader};
+    use crate::token::signed::SignWithKey;
+    use crate::token::verified::VerifyWithKey;
+    use crate::Token;
+
+    /// HMAC with SHA-256 under a private algorithm name.
+    struct PrivateHmac(Hmac<Sha256>);
+
+    impl SigningAlgorithm for PrivateHmac {
+        fn algorithm_type(&self) -> AlgorithmType {
+            AlgorithmType::Other("X-HS256".to_owned())
+        }
+
+        fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+            self.0.sign(header, claims)
+        }
+    }
+
+    impl VerifyingAlgorithm for PrivateHmac {
+        fn algorithm_type(&self) -> AlgorithmType {
+            AlgorithmType::Other("X-HS256".to_owned())
+        }
+
+        fn verify_bytes(
+            &self,
+            header: &str,
+            claims: &str,
+            signature: &[u8],
+        ) -> Result<bool, Error> {
+            self.0.verify_bytes(header, claims, signature)
+        }
+    }
+
+    #[test]
+    fn key_pair_algorithm_mismatch() -> Result<(), Error> {
+        let hs256_key: Hmac<Sha256> = Hmac::new_from_slice(b"secret")?;
+        let hs512_key: Hmac<Sha512> = Hmac::new_from_slice(b"secret")?;
+        match KeyPair::new(hs256_key, hs512_key) {
+            Err(Error::AlgorithmMismatch(AlgorithmType::Hs256, AlgorithmType::Hs512)) => Ok(()),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Keys with different algorithms should not be paired"),
+        }
+    }
+
+    #[test]
+    fn names() {
+        let algorithms = [
+            (AlgorithmType::Hs256, "HS256"),
+            (AlgorithmType::Es256k, "ES256K"),
+            (AlgorithmType::EdDsa, "EdDSA"),
+            (AlgorithmType::None, "none"),
+            (AlgorithmType::Other("X-HS256".to_owned()), "X-HS256"),
+        ];
+
+        for (algorithm, name) in algorithms.iter() {
+            assert_eq!(algorithm.to_string(), *name);
+            assert_eq!(name.parse::<AlgorithmType>().unwrap(), *algorithm);
+        }
+
+        // Names are case sensitive
+        assert_eq!(
+            "hs256".parse::<AlgorithmType>().unwrap(),
+            AlgorithmType::Other("hs256".to_owned())
+        );
+    }
+
+    #[test]
+    fn custom_algorithm() -> Result<(), Error> {
+        let key = PrivateHmac(Hmac::new_from_slice(b"secret")?);
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+
+        let token_str = claims.sign_with_key(&key)?;
+        let token: Token<Header, BTreeMap<String, String>, _> =
+            VerifyWithKey::verify_with_key(token_str.as_str(), &key)?;
+        assert_eq!(
+            token.header().algorithm_type(),
+            AlgorithmType::Other("X-HS256".to_owned())
+        );
+
+        let hs256_key: Hmac<Sha256> = Hmac::new_from_slice(b"secret")?;
+        let result: Result<BTreeMap<String, String>, Error> =
+            token_str.as_str().verify_with_key(&hs256_key);
+        match result {
+            Err(Error::AlgorithmMismatch(_, _)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
 }
@@ -1,49 +1,168 @@
 //! OpenSSL support through the openssl crate.
 //! Note that private keys can only be used for signing and that public keys
-//! can only be used for verification.
+//! can only be used for verification. RSA keys sign with PKCS #1 v1.5 padding
+//! when wrapped in `PKeyWithDigest` and with RSASSA-PSS padding when wrapped in
+//! `PssPKeyWithDigest`. Ed25519 and Ed448 keys do not use a separate digest and
+//! are wrapped in `EdDsaPKey`. ECDSA signatures use the fixed width JOSE
+//! format, and `LowSPKeyWithDigest` additionally restricts them to the low-S
+//! form.
+//!
+//! The `new` constructors check that the key and digest form a supported
+//! algorithm and that RSA keys have at least 2048 bits, returning
+//! `Error::InvalidKey` otherwise. Wrappers built directly from their public
+//! fields are not checked when they are built. With an unsupported pairing
+//! they report an `Other` algorithm type, and signing and verifying fail with
+//! `Error::InvalidKey`.
 //! ## Examples
 //! ```
 //! use jwt::PKeyWithDigest;
 //! use openssl::hash::MessageDigest;
 //! use openssl::pkey::PKey;
-//! let pem = include_bytes!("../../test/rs256-public.pem");
-//! let rs256_public_key = PKeyWithDigest {
-//!     digest: MessageDigest::sha256(),
-//!     key: PKey::public_key_from_pem(pem).unwrap(),
-//! };
+//! let pem = include_bytes!("../../test/rs2048-public.pem");
+//! let rs256_public_key = PKeyWithDigest::new(
+//!     PKey::public_key_from_pem(pem).unwrap(),
+//!     MessageDigest::sha256(),
+//! ).unwrap();
 //! ```
+//! ```
+//! use jwt::PssPKeyWithDigest;
+//! use openssl::hash::MessageDigest;
+//! use openssl::pkey::PKey;
+//! let pem = include_bytes!("../../test/rs2048-public.pem");
+//! let ps256_public_key = PssPKeyWithDigest::new(
+//!     PKey::public_key_from_pem(pem).unwrap(),
+//!     MessageDigest::sha256(),
+//! ).unwrap();
+//! ```
+//! ```
+//! use jwt::EdDsaPKey;
+//! use openssl::pkey::PKey;
+//! let pem = include_bytes!("../../test/ed25519-public.pem");
+//! let ed25519_public_key = EdDsaPKey::new(PKey::public_key_from_pem(pem).unwrap()).unwrap();
+//! ```
+//! A `KeyPair` derives the public key from a private key and can both sign
+//! and verify.
+//! ```
+//! use jwt::{KeyPair, PKeyWithDigest};
+//! use openssl::hash::MessageDigest;
+//! use openssl::pkey::PKey;
+//! let pem = include_bytes!("../../test/rs2048-private.pem");
+//! let rs256_key_pair = KeyPair::<PKeyWithDigest<_>, _>::from_private_key(
+//!     PKey::private_key_from_pem(pem).unwrap(),
+//!     MessageDigest::sha256(),
+//! ).unwrap();
+//! ```
+//! The loaders detect the key type, encoding and curve from PEM or DER input
+//! and pick the algorithm, unless one is requested.
+//! ```
+//! use jwt::algorithm::openssl::{signing_key_from_pem, verifying_key_from_pem};
+//! use jwt::AlgorithmType;
+//! let private_pem = include_bytes!("../../test/es256-private.pem");
+//! let es256_signing_key = signing_key_from_pem(private_pem, None).unwrap();
+//! let public_pem = include_bytes!("../../test/rs2048-public.pem");
+//! let ps384_public_key =
+//!     verifying_key_from_pem(public_pem, Some(AlgorithmType::Ps384)).unwrap();
+//! ```
+//! Passphrase protected private keys, as encrypted PKCS #8 or PKCS #12, are
+//! loaded with a callback that provides the passphrase.
+//! ```
+//! use jwt::algorithm::openssl::signing_key_from_encrypted_pem;
+//! let pem = include_bytes!("../../test/es256-private-encrypted.pem");
+//! let es256_signing_key =
+//!     signing_key_from_encrypted_pem(pem, || Ok("secret".to_owned()), None).unwrap();
+//! ```
+//! With this feature, `GeneratedKey` creates RSA, ECDSA and EdDSA keys with
+//! OpenSSL.
+//! ```
+//! use jwt::{AlgorithmType, GeneratedKey, ToJwk};
+//! let key = GeneratedKey::generate(AlgorithmType::Es256).unwrap();
+//! let pem = key.private_key_to_pem().unwrap();
+//! let jwk = key.to_jwk().unwrap();
+//! assert_eq!(jwk.key_id, Some(key.key_id));
+//! ```
+
+use std::convert::TryFrom;
+use std::os::raw::c_int;
 
-use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+use crate::algorithm::generate::GeneratedKey;
+use crate::algorithm::{AlgorithmType, KeyPair, SigningAlgorithm, VerifyingAlgorithm};
 use crate::error::Error;
+use crate::jwk::{
+    private_parameter, EllipticCurve, EllipticCurveParameters, Jwk, KeyParameters,
+    OctetKeyPairCurve, OctetKeyPairParameters, RsaParameters, ToJwk,
+};
 use crate::SEPARATOR;
 
-use openssl::bn::BigNum;
+use openssl::bn::{BigNum, BigNumContext, BigNumRef};
+use openssl::ec::{EcGroup, EcGroupRef, EcKey};
 use openssl::ecdsa::EcdsaSig;
+use openssl::error::ErrorStack;
 use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
-use openssl::pkey::{Id, PKey, Private, Public};
-use openssl::sign::{Signer, Verifier};
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{HasParams, HasPrivate, HasPublic, Id, PKey, PKeyRef, Private, Public};
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::{RsaPssSaltlen, Signer, Verifier};
+use openssl::x509::X509;
 
 /// A wrapper class around [PKey](../../../openssl/pkey/struct.PKey.html) that
 /// associates the key with a
 /// [MessageDigest](../../../openssl/hash/struct.MessageDigest.html).
+///
+/// Prefer [`PKeyWithDigest::new`], which rejects unsupported combinations of
+/// key and digest. A wrapper built from its fields with such a combination
+/// reports an `Other` algorithm type and fails to sign or verify.
 pub struct PKeyWithDigest<T> {
     pub digest: MessageDigest,
     pub key: PKey<T>,
 }
 
-impl<T> PKeyWithDigest<T> {
-    fn algorithm_type(&self) -> AlgorithmType {
-        match (self.key.id(), self.digest.type_()) {
-            (Id::RSA, Nid::SHA256) => AlgorithmType::Rs256,
-            (Id::RSA, Nid::SHA384) => AlgorithmType::Rs384,
-            (Id::RSA, Nid::SHA512) => AlgorithmType::Rs512,
-            (Id::EC, Nid::SHA256) => AlgorithmType::Es256,
-            (Id::EC, Nid::SHA384) => AlgorithmType::Es384,
-            (Id::EC, Nid::SHA512) => AlgorithmType::Es512,
-            _ => panic!("Invalid algorithm type"),
+impl<T: HasParams> PKeyWithDigest<T> {
+    /// Pair a key with a digest, checking that the two form a supported
+    /// algorithm. EC keys must be on the curve that matches the digest and RSA
+    /// keys must have at least 2048 bits.
+    pub fn new(key: PKey<T>, digest: MessageDigest) -> Result<Self, Error>
+    where
+        T: HasPublic,
+    {
+        let key = PKeyWithDigest { digest, key };
+        key.checked_algorithm_type()?;
+        check_rsa_key_size(&key.key)?;
+        Ok(key)
+    }
+
+    fn checked_algorithm_type(&self) -> Result<AlgorithmType, Error> {
+        let digest = self.digest.type_();
+        match (self.key.id(), digest) {
+            (Id::RSA, Nid::SHA256) => Ok(AlgorithmType::Rs256),
+            (Id::RSA, Nid::SHA384) => Ok(AlgorithmType::Rs384),
+            (Id::RSA, Nid::SHA512) => Ok(AlgorithmType::Rs512),
+            (Id::EC, _) => {
+                let curve = self.key.ec_key()?.group().curve_name();
+                match (curve, digest) {
+                    (Some(Nid::X9_62_PRIME256V1), Nid::SHA256) => Ok(AlgorithmType::Es256),
+                    (Some(Nid::SECP256K1), Nid::SHA256) => Ok(AlgorithmType::Es256k),
+                    (Some(Nid::SECP384R1), Nid::SHA384) => Ok(AlgorithmType::Es384),
+                    (Some(Nid::SECP521R1), Nid::SHA512) => Ok(AlgorithmType::Es512),
+                    _ => Err(Error::InvalidKey(format!(
+                        "EC curve {} can not be used with digest {}",
+                        curve.map_or("unknown", nid_name),
+                        nid_name(digest),
+                    ))),
+                }
+            }
+            (id, _) => Err(Error::InvalidKey(format!(
+                "key type {} can not be used with digest {}",
+                nid_name(Nid::from_raw(id.as_raw())),
+                nid_name(digest),
+            ))),
         }
     }
+
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.checked_algorithm_type()
+            .unwrap_or_else(|_| invalid_algorithm_type())
+    }
 }
 
 impl SigningAlgorithm for PKeyWithDigest<Private> {
@@ -52,20 +171,15 @@ impl SigningAlgorithm for PKeyWithDigest<Private> {
     }
 
     fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
-        let mut signer = Signer::new(self.digest.clone(), &self.key)?;
-        signer.update(header.as_bytes())?;
-        signer.update(SEPARATOR.as_bytes())?;
-        signer.update(claims.as_bytes())?;
-        let signer_signature = signer.sign_to_vec()?;
-
-        let signature = if self.key.id() == Id::EC {
-            der_to_jose(&signer_signature)?
-        } else {
-            signer_signature
-        };
-
+        let signature = self.sign_bytes(header, claims)?;
         Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
     }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.checked_algorithm_type()?;
+        let signer = Signer::new(self.digest, &self.key)?;
+        sign(signer, &self.key, header, claims, false)
+    }
 }
 
 impl VerifyingAlgorithm for PKeyWithDigest<Public> {
@@ -74,106 +188,2054 @@ impl VerifyingAlgorithm for PKeyWithDigest<Public> {
     }
 
     fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
-        let mut verifier = Verifier::new(self.digest.clone(), &self.key)?;
-        verifier.update(header.as_bytes())?;
-        verifier.update(SEPARATOR.as_bytes())?;
-        verifier.update(claims.as_bytes())?;
+        self.checked_algorithm_type()?;
+        let verifier = Verifier::new(self.digest, &self.key)?;
+        verify(verifier, &self.key, header, claims, signature, false)
+    }
+}
 
-        let verified = if self.key.id() == Id::EC {
-            let der = jose_to_der(signature)?;
-            verifier.verify(&der)?
-        } else {
-            verifier.verify(signature)?
-        };
+/// A wrapper class around an EC `PKeyWithDigest` that only produces and
+/// accepts ECDSA signatures in the low-S form, where S is at most half of the
+/// curve order. Signatures are normalized when signing and signatures with a
+/// high S are rejected when verifying, which prevents the same token from
+/// having two valid signatures.
+pub struct LowSPKeyWithDigest<T> {
+    inner: PKeyWithDigest<T>,
+}
+
+impl<T: HasParams> LowSPKeyWithDigest<T> {
+    /// Pair an EC key with a digest, with the same checks as
+    /// `PKeyWithDigest::new`.
+    pub fn new(key: PKey<T>, digest: MessageDigest) -> Result<Self, Error>
+    where
+        T: HasPublic,
+    {
+        if key.id() != Id::EC {
+            return Err(Error::InvalidKey(format!(
+                "key type {} can not be used for ECDSA",
+                nid_name(Nid::from_raw(key.id().as_raw())),
+            )));
+        }
+
+        let inner = PKeyWithDigest::new(key, digest)?;
+        Ok(LowSPKeyWithDigest { inner })
+    }
+
+    /// The wrapped key.
+    pub fn key(&self) -> &PKeyWithDigest<T> {
+        &self.inner
+    }
+}
+
+impl SigningAlgorithm for LowSPKeyWithDigest<Private> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.inner.algorithm_type()
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let signer = Signer::new(self.inner.digest, &self.inner.key)?;
+        sign(signer, &self.inner.key, header, claims, true)
+    }
+}
+
+impl VerifyingAlgorithm for LowSPKeyWithDigest<Public> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.inner.algorithm_type()
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        let verifier = Verifier::new(self.inner.digest, &self.inner.key)?;
+        verify(verifier, &self.inner.key, header, claims, signature, true)
+    }
+}
+
+/// A wrapper class around an RSA [PKey](../../../openssl/pkey/struct.PKey.html)
+/// that signs and verifies with RSASSA-PSS padding, as used by the `PS256`,
+/// `PS384` and `PS512` algorithms. MGF1 uses the same
+/// [MessageDigest](../../../openssl/hash/struct.MessageDigest.html) as the
+/// signature and the salt length is equal to the digest size.
+pub struct PssPKeyWithDigest<T> {
+    pub digest: MessageDigest,
+    pub key: PKey<T>,
+}
+
+impl<T: HasParams> PssPKeyWithDigest<T> {
+    /// Pair an RSA key with a digest, checking that the two form a supported
+    /// algorithm and that the key has at least 2048 bits.
+    pub fn new(key: PKey<T>, digest: MessageDigest) -> Result<Self, Error>
+    where
+        T: HasPublic,
+    {
+        let key = PssPKeyWithDigest { digest, key };
+        key.checked_algorithm_type()?;
+        check_rsa_key_size(&key.key)?;
+        Ok(key)
+    }
+
+    fn checked_algorithm_type(&self) -> Result<AlgorithmType, Error> {
+        let digest = self.digest.type_();
+        match (self.key.id(), digest) {
+            (Id::RSA, Nid::SHA256) | (Id::RSA_PSS, Nid::SHA256) => Ok(AlgorithmType::Ps256),
+            (Id::RSA, Nid::SHA384) | (Id::RSA_PSS, Nid::SHA384) => Ok(AlgorithmType::Ps384),
+            (Id::RSA, Nid::SHA512) | (Id::RSA_PSS, Nid::SHA512) => Ok(AlgorithmType::Ps512),
+            (id, _) => Err(Error::InvalidKey(format!(
+                "key type {} can not be used for RSASSA-PSS with digest {}",
+                nid_name(Nid::from_raw(id.as_raw())),
+                nid_name(digest),
+            ))),
+        }
+    }
+
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.checked_algorithm_type()
+            .unwrap_or_else(|_| invalid_algorithm_type())
+    }
+}
+
+impl SigningAlgorithm for PssPKeyWithDigest<Private> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        PssPKeyWithDigest::algorithm_type(self)
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.checked_algorithm_type()?;
+        let mut signer = Signer::new(self.digest, &self.key)?;
+        signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+        signer.set_rsa_mgf1_md(self.digest)?;
+        signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+        sign(signer, &self.key, header, claims, false)
+    }
+}
+
+impl VerifyingAlgorithm for PssPKeyWithDigest<Public> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        PssPKeyWithDigest::algorithm_type(self)
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        self.checked_algorithm_type()?;
+        let mut verifier = Verifier::new(self.digest, &self.key)?;
+        verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+        verifier.set_rsa_mgf1_md(self.digest)?;
+        verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+        verify(verifier, &self.key, header, claims, signature, false)
+    }
+}
+
+/// A wrapper class around an Ed25519 or Ed448
+/// [PKey](../../../openssl/pkey/struct.PKey.html) for the `EdDSA` algorithm
+/// defined in [RFC 8037](https://tools.ietf.org/html/rfc8037). EdDSA hashes
+/// the message itself, so no digest is associated with the key.
+pub struct EdDsaPKey<T> {
+    pub key: PKey<T>,
+}
+
+impl<T> EdDsaPKey<T> {
+    /// Wrap a key, checking that it is an Ed25519 or Ed448 key.
+    pub fn new(key: PKey<T>) -> Result<Self, Error> {
+        let key = EdDsaPKey { key };
+        key.checked_algorithm_type()?;
+        Ok(key)
+    }
+
+    fn checked_algorithm_type(&self) -> Result<AlgorithmType, Error> {
+        match self.key.id() {
+            Id::ED25519 | Id::ED448 => Ok(AlgorithmType::EdDsa),
+            id => Err(Error::InvalidKey(format!(
+                "key type {} can not be used for EdDSA",
+                nid_name(Nid::from_raw(id.as_raw())),
+            ))),
+        }
+    }
+
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.checked_algorithm_type()
+            .unwrap_or_else(|_| invalid_algorithm_type())
+    }
+}
+
+impl SigningAlgorithm for EdDsaPKey<Private> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        EdDsaPKey::algorithm_type(self)
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        self.checked_algorithm_type()?;
+        let mut signer = Signer::new_without_digest(&self.key)?;
+        let signature = signer.sign_oneshot_to_vec(signing_input(header, claims).as_bytes())?;
+        Ok(signature)
+    }
+}
+
+impl VerifyingAlgorithm for EdDsaPKey<Public> {
+    fn algorithm_type(&self) -> AlgorithmType {
+        EdDsaPKey::algorithm_type(self)
+    }
 
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        self.checked_algorithm_type()?;
+        let mut verifier = Verifier::new_without_digest(&self.key)?;
+        let verified =
+            verifier.verify_oneshot(signature, signing_input(header, claims).as_bytes())?;
         Ok(verified)
     }
 }
 
-/// OpenSSL by default signs ECDSA in DER, but JOSE expects them in a concatenated (R, S) format
-fn der_to_jose(der: &[u8]) -> Result<Vec<u8>, Error> {
-    let signature = EcdsaSig::from_der(&der)?;
-    let r = signature.r().to_vec();
-    let s = signature.s().to_vec();
-    Ok([r, s].concat())
+impl KeyPair<PKeyWithDigest<Private>, PKeyWithDigest<Public>> {
+    /// Create a key pair from a private RSA or EC key, deriving the public key
+    /// from it. The key and digest are checked as in `PKeyWithDigest::new`.
+    pub fn from_private_key(key: PKey<Private>, digest: MessageDigest) -> Result<Self, Error> {
+        let public_key = PKeyWithDigest::new(public_key(&key)?, digest)?;
+        KeyPair::new(PKeyWithDigest { digest, key }, public_key)
+    }
 }
 
-/// OpenSSL by default verifies ECDSA in DER, but JOSE parses out a concatenated (R, S) format
-fn jose_to_der(jose: &[u8]) -> Result<Vec<u8>, Error> {
-    let (r, s) = jose.split_at(jose.len() / 2);
-    let ecdsa_signature =
-        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
-    Ok(ecdsa_signature.to_der()?)
+impl KeyPair<LowSPKeyWithDigest<Private>, LowSPKeyWithDigest<Public>> {
+    /// Create a key pair from a private EC key, deriving the public key from
+    /// it. The key and digest are checked as in `LowSPKeyWithDigest::new`.
+    pub fn from_private_key(key: PKey<Private>, digest: MessageDigest) -> Result<Self, Error> {
+        let public_key = LowSPKeyWithDigest::new(public_key(&key)?, digest)?;
+        let inner = PKeyWithDigest { digest, key };
+        KeyPair::new(LowSPKeyWithDigest { inner }, public_key)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::algorithm::openssl::PKeyWithDigest;
-    use crate::algorithm::AlgorithmType::*;
-    use crate::algorithm::{SigningAlgorithm, VerifyingAlgorithm};
-    use crate::error::Error;
-    use crate::header::PrecomputedAlgorithmOnlyHeader as AlgOnly;
-    use crate::ToBase64;
+impl KeyPair<PssPKeyWithDigest<Private>, PssPKeyWithDigest<Public>> {
+    /// Create a key pair from a private RSA key, deriving the public key from
+    /// it. The key and digest are checked as in `PssPKeyWithDigest::new`.
+    pub fn from_private_key(key: PKey<Private>, digest: MessageDigest) -> Result<Self, Error> {
+        let public_key = PssPKeyWithDigest::new(public_key(&key)?, digest)?;
+        KeyPair::new(PssPKeyWithDigest { digest, key }, public_key)
+    }
+}
 
-    use openssl::hash::MessageDigest;
-    use openssl::pkey::PKey;
+impl KeyPair<EdDsaPKey<Private>, EdDsaPKey<Public>> {
+    /// Create a key pair from a private Ed25519 or Ed448 key, deriving the
+    /// public key from it.
+    pub fn from_private_key(key: PKey<Private>) -> Result<Self, Error> {
+        let public_key = EdDsaPKey::new(public_key(&key)?)?;
+        KeyPair::new(EdDsaPKey { key }, public_key)
+    }
+}
 
-    // {"sub":"1234567890","name":"John Doe","admin":true}
-    const CLAIMS: &'static str =
-        "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+/// Load a signing key from a PEM encoded PKCS #8, PKCS #1 (RSA) or SEC 1 (EC)
+/// private key. The key type and curve are detected from the key, and the
+/// algorithm defaults to RS256 for RSA keys, to the algorithm of the curve for
+/// EC keys and to EdDSA for Ed25519 and Ed448 keys. Pass `algorithm` to pick
+/// another digest or RSASSA-PSS, or to check that the key is the expected one.
+pub fn signing_key_from_pem(
+    pem: &[u8],
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error> {
+    if is_encrypted_pem(pem)? {
+        return Err(encrypted_key());
+    }
+    let key = match pem_label(pem)? {
+        "PRIVATE KEY" | "RSA PRIVATE KEY" | "EC PRIVATE KEY" => PKey::private_key_from_pem(pem)?,
+        label => return Err(unsupported_pem_label(label, "signing")),
+    };
+    boxed_signing_key(key, algorithm)
+}
 
-    const RS256_SIGNATURE: &'static str =
-    "cQsAHF2jHvPGFP5zTD8BgoJrnzEx6JNQCpupebWLFnOc2r_punDDTylI6Ia4JZNkvy2dQP-7W-DEbFQ3oaarHsDndqUgwf9iYlDQxz4Rr2nEZX1FX0-FMEgFPeQpdwveCgjtTYUbVy37ijUySN_rW-xZTrsh_Ug-ica8t-zHRIw";
+/// Load a signing key from a DER encoded PKCS #8, PKCS #1 (RSA) or SEC 1 (EC)
+/// private key. The algorithm is chosen as in `signing_key_from_pem`.
+pub fn signing_key_from_der(
+    der: &[u8],
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error> {
+    let key = PKey::private_key_from_der(der)
+        .map_err(|_| Error::InvalidKey("DER input is not a supported private key".to_owned()))?;
+    boxed_signing_key(key, algorithm)
+}
 
-    #[test]
-    fn rs256_sign() -> Result<(), Error> {
-        let pem = include_bytes!("../../test/rs256-private.pem");
+/// Load a verifying key from a PEM encoded SubjectPublicKeyInfo, PKCS #1 RSA
+/// public key or X.509 certificate. Private keys are accepted as well and
+/// only their public half is kept. The algorithm is chosen as in
+/// `signing_key_from_pem`.
+pub fn verifying_key_from_pem(
+    pem: &[u8],
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn VerifyingAlgorithm + Send + Sync>, Error> {
+    if is_encrypted_pem(pem)? {
+        return Err(encrypted_key());
+    }
+    let key = match pem_label(pem)? {
+        "PUBLIC KEY" => PKey::public_key_from_pem(pem)?,
+        "RSA PUBLIC KEY" => PKey::from_rsa(Rsa::public_key_from_pem_pkcs1(pem)?)?,
+        "CERTIFICATE" => X509::from_pem(pem)?.public_key()?,
+        "PRIVATE KEY" | "RSA PRIVATE KEY" | "EC PRIVATE KEY" => {
+            let private_key = PKey::private_key_from_pem(pem)?;
+            public_key(&private_key)?
+        }
+        label => return Err(unsupported_pem_label(label, "verifying")),
+    };
+    boxed_verifying_key(key, algorithm)
+}
 
-        let algorithm = PKeyWithDigest {
-            digest: MessageDigest::sha256(),
-            key: PKey::private_key_from_pem(pem)?,
-        };
+/// Load a verifying key from a DER encoded SubjectPublicKeyInfo, PKCS #1 RSA
+/// public key, X.509 certificate or private key. The algorithm is chosen as in
+/// `signing_key_from_pem`.
+pub fn verifying_key_from_der(
+    der: &[u8],
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn VerifyingAlgorithm + Send + Sync>, Error> {
+    let key = PKey::public_key_from_der(der)
+        .or_else(|_| PKey::from_rsa(Rsa::public_key_from_der_pkcs1(der)?))
+        .or_else(|_| X509::from_der(der)?.public_key())
+        .or_else(|_| {
+            let private_key = PKey::private_key_from_der(der)?;
+            PKey::public_key_from_der(&private_key.public_key_to_der()?)
+        })
+        .map_err(|_| Error::InvalidKey("DER input is not a supported key".to_owned()))?;
+    boxed_verifying_key(key, algorithm)
+}
 
-        let result = algorithm.sign(&AlgOnly(Rs256).to_base64()?, CLAIMS)?;
-        assert_eq!(result, RS256_SIGNATURE);
-        Ok(())
+/// Load a signing key from a passphrase protected PEM private key: either an
+/// encrypted PKCS #8 key or a legacy PKCS #1 or SEC 1 key with a `DEK-Info`
+/// header. `passphrase` is only called once the key is known to be encrypted.
+/// A wrong passphrase is reported as `Error::InvalidPassphrase`, an
+/// encryption scheme that OpenSSL does not provide as
+/// `Error::UnsupportedKeyEncryption` and corrupt input as
+/// `Error::InvalidKey`. The algorithm is chosen as in `signing_key_from_pem`.
+pub fn signing_key_from_encrypted_pem<F>(
+    pem: &[u8],
+    passphrase: F,
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error>
+where
+    F: FnOnce() -> Result<String, Error>,
+{
+    match pem_label(pem)? {
+        "ENCRYPTED PRIVATE KEY" | "RSA PRIVATE KEY" | "EC PRIVATE KEY" => (),
+        label => return Err(unsupported_pem_label(label, "signing")),
+    }
+    if !is_encrypted_pem(pem)? {
+        return Err(Error::InvalidKey(
+            "PEM private key is not encrypted".to_owned(),
+        ));
     }
 
-    #[test]
-    fn rs256_verify() -> Result<(), Error> {
-        let pem = include_bytes!("../../test/rs256-public.pem");
+    let key = PKey::private_key_from_pem_passphrase(pem, passphrase()?.as_bytes())
+        .map_err(decryption_error)?;
+    boxed_signing_key(key, algorithm)
+}
 
-        let algorithm = PKeyWithDigest {
-            digest: MessageDigest::sha256(),
-            key: PKey::public_key_from_pem(pem)?,
+/// Load a signing key from a DER encoded, encrypted PKCS #8 private key. The
+/// passphrase and errors are handled as in `signing_key_from_encrypted_pem`.
+pub fn signing_key_from_encrypted_der<F>(
+    der: &[u8],
+    passphrase: F,
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error>
+where
+    F: FnOnce() -> Result<String, Error>,
+{
+    if PKey::private_key_from_der(der).is_ok() {
+        return Err(Error::InvalidKey(
+            "DER private key is not encrypted".to_owned(),
+        ));
+    }
+
+    let key = PKey::private_key_from_pkcs8_passphrase(der, passphrase()?.as_bytes())
+        .map_err(decryption_error)?;
+    boxed_signing_key(key, algorithm)
+}
+
+/// Load the signing key from a DER encoded PKCS #12 archive. Certificates in
+/// the archive are ignored. The passphrase and errors are handled as in
+/// `signing_key_from_encrypted_pem`.
+pub fn signing_key_from_pkcs12<F>(
+    der: &[u8],
+    passphrase: F,
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error>
+where
+    F: FnOnce() -> Result<String, Error>,
+{
+    let archive = Pkcs12::from_der(der)
+        .map_err(|_| Error::InvalidKey("input is not a PKCS #12 archive".to_owned()))?;
+    let key = archive
+        .parse2(&passphrase()?)
+        .map_err(decryption_error)?
+        .pkey
+        .ok_or_else(|| {
+            Error::InvalidKey("PKCS #12 archive does not contain a private key".to_owned())
+        })?;
+    boxed_signing_key(key, algorithm)
+}
+
+/// Generate an RSA, ECDSA or EdDSA key for `GeneratedKey::generate`.
+pub(crate) fn generate_key(algorithm: AlgorithmType) -> Result<GeneratedKey, Error> {
+    use AlgorithmType::*;
+
+    let curve = |nid| -> Result<PKey<Private>, Error> {
+        let group = EcGroup::from_curve_name(nid)?;
+        Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+    };
+    let key = match algorithm {
+        Rs256 | Rs384 | Rs512 | Ps256 | Ps384 | Ps512 => {
+            PKey::from_rsa(Rsa::generate(MIN_RSA_BITS)?)?
+        }
+        Es256 => curve(Nid::X9_62_PRIME256V1)?,
+        Es256k => curve(Nid::SECP256K1)?,
+        Es384 => curve(Nid::SECP384R1)?,
+        Es512 => curve(Nid::SECP521R1)?,
+        EdDsa => PKey::generate_ed25519()?,
+        other => return Err(Error::UnsupportedAlgorithm(other)),
+    };
+
+    let public_key = public_key(&key)?;
+    Ok(GeneratedKey::from_key_pair(
+        boxed_signing_key(key.clone(), Some(algorithm.clone()))?,
+        boxed_verifying_key(public_key.clone(), Some(algorithm.clone()))?,
+        Jwk::signature_key(private_jwk_parameters(&key)?, algorithm.clone()),
+        Jwk::signature_key(jwk_parameters(&public_key)?, algorithm),
+        key.private_key_to_pem_pkcs8()?,
+        key.public_key_to_pem()?,
+    ))
+}
+
+/// A key wrapped for the algorithm chosen by `wrap_key`.
+enum WrappedKey<T> {
+    Digest(PKeyWithDigest<T>),
+    Pss(PssPKeyWithDigest<T>),
+    EdDsa(EdDsaPKey<T>),
+}
+
+fn boxed_signing_key(
+    key: PKey<Private>,
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn SigningAlgorithm + Send + Sync>, Error> {
+    Ok(match wrap_key(key, algorithm)? {
+        WrappedKey::Digest(key) => Box::new(key),
+        WrappedKey::Pss(key) => Box::new(key),
+        WrappedKey::EdDsa(key) => Box::new(key),
+    })
+}
+
+fn boxed_verifying_key(
+    key: PKey<Public>,
+    algorithm: Option<AlgorithmType>,
+) -> Result<Box<dyn VerifyingAlgorithm + Send + Sync>, Error> {
+    Ok(match wrap_key(key, algorithm)? {
+        WrappedKey::Digest(key) => Box::new(key),
+        WrappedKey::Pss(key) => Box::new(key),
+        WrappedKey::EdDsa(key) => Box::new(key),
+    })
+}
+
+/// Pick the algorithm for a key, or check that the requested one fits it, and
+/// wrap the key with the matching digest.
+fn wrap_key<T: HasPublic + HasParams>(
+    key: PKey<T>,
+    algorithm: Option<AlgorithmType>,
+) -> Result<WrappedKey<T>, Error> {
+    use AlgorithmType::*;
+
+    let detected = match key.id() {
+        Id::RSA => Rs256,
+        Id::RSA_PSS => Ps256,
+        Id::EC => match key.ec_key()?.group().curve_name() {
+            Some(Nid::X9_62_PRIME256V1) => Es256,
+            Some(Nid::SECP256K1) => Es256k,
+            Some(Nid::SECP384R1) => Es384,
+            Some(Nid::SECP521R1) => Es512,
+            curve => {
+                return Err(Error::InvalidKey(format!(
+                    "EC curve {} is not supported",
+                    curve.map_or("unknown", nid_name),
+                )))
+            }
+        },
+        Id::ED25519 | Id::ED448 => EdDsa,
+        id => {
+            return Err(Error::InvalidKey(format!(
+                "key type {} is not supported",
+                nid_name(Nid::from_raw(id.as_raw())),
+            )))
+        }
+    };
+
+    let algorithm = algorithm.unwrap_or_else(|| detected.clone());
+    let compatible = match detected {
+        Rs256 => matches!(algorithm, Rs256 | Rs384 | Rs512 | Ps256 | Ps384 | Ps512),
+        Ps256 => matches!(algorithm, Ps256 | Ps384 | Ps512),
+        _ => algorithm == detected,
+    };
+    if !compatible {
+        return match algorithm {
+            Rs256 | Rs384 | Rs512 | Ps256 | Ps384 | Ps512 | Es256 | Es256k | Es384 | Es512
+            | EdDsa => Err(Error::InvalidKey(format!(
+                "key type {} can not be used with {:?}",
+                nid_name(Nid::from_raw(key.id().as_raw())),
+                algorithm,
+            ))),
+            other => Err(Error::UnsupportedAlgorithm(other)),
         };
+    }
 
-        let verification_result =
-            algorithm.verify(&AlgOnly(Rs256).to_base64()?, CLAIMS, RS256_SIGNATURE)?;
-        assert!(verification_result);
-        Ok(())
+    Ok(match algorithm {
+        Rs256 | Rs384 | Rs512 | Es256 | Es256k | Es384 | Es512 => {
+            WrappedKey::Digest(PKeyWithDigest::new(key, algorithm_digest(&algorithm)?)?)
+        }
+        Ps256 | Ps384 | Ps512 => {
+            WrappedKey::Pss(PssPKeyWithDigest::new(key, algorithm_digest(&algorithm)?)?)
+        }
+        _ => WrappedKey::EdDsa(EdDsaPKey::new(key)?),
+    })
+}
+
+/// The label of the first PEM block, skipping any EC parameters that precede
+/// a SEC 1 private key.
+fn pem_label(pem: &[u8]) -> Result<&str, Error> {
+    const BEGIN: &str = "-----BEGIN ";
+
+    let mut text = std::str::from_utf8(pem)
+        .map_err(|_| Error::InvalidKey("PEM input is not valid UTF-8".to_owned()))?;
+    while let Some(start) = text.find(BEGIN) {
+        text = &text[start + BEGIN.len()..];
+        let label = text
+            .find("-----")
+            .map(|end| &text[..end])
+            .ok_or_else(|| Error::InvalidKey("PEM input has a malformed header".to_owned()))?;
+        if label != "EC PARAMETERS" {
+            return Ok(label);
+        }
     }
+    Err(Error::InvalidKey(
+        "input does not contain a PEM block".to_owned(),
+    ))
+}
 
-    #[test]
-    fn es256() -> Result<(), Error> {
-        let private_pem = include_bytes!("../../test/es256-private.pem");
-        let private_key = PKeyWithDigest {
-            digest: MessageDigest::sha256(),
-            key: PKey::private_key_from_pem(private_pem)?,
-        };
+/// Whether a PEM private key is encrypted, either as PKCS #8 or with the
+/// legacy `Proc-Type` header.
+fn is_encrypted_pem(pem: &[u8]) -> Result<bool, Error> {
+    let label = pem_label(pem)?;
+    Ok(label == "ENCRYPTED PRIVATE KEY"
+        || String::from_utf8_lossy(pem).contains("Proc-Type: 4,ENCRYPTED"))
+}
 
-        let signature = private_key.sign(&AlgOnly(Es256).to_base64()?, CLAIMS)?;
+fn encrypted_key() -> Error {
+    Error::InvalidKey("encrypted private keys must be loaded with a passphrase".to_owned())
+}
 
-        let public_pem = include_bytes!("../../test/es256-public.pem");
+/// OpenSSL library codes, from `err.h`.
+const ERR_LIB_EVP: c_int = 6;
+const ERR_LIB_PEM: c_int = 9;
+const ERR_LIB_PKCS12: c_int = 35;
+const ERR_LIB_PROV: c_int = 57;
 
-        let public_key = PKeyWithDigest {
-            digest: MessageDigest::sha256(),
-            key: PKey::public_key_from_pem(public_pem)?,
-        };
+/// The reason code that OpenSSL 3 reports for algorithms that no loaded
+/// provider implements, `ERR_R_UNSUPPORTED` in `err.h`.
+const ERR_R_UNSUPPORTED: c_int = 268 | (0x2 << 18);
 
-        let verification_result =
-            public_key.verify(&AlgOnly(Es256).to_base64()?, CLAIMS, &*signature)?;
-        assert!(verification_result);
-        Ok(())
+/// The library and reason codes of a failed decryption or MAC check, from
+/// `evperr.h`, `pemerr.h`, `pkcs12err.h` and `proverr.h`.
+const WRONG_PASSPHRASE: &[(c_int, c_int)] = &[
+    (ERR_LIB_EVP, 100),    // EVP_R_BAD_DECRYPT
+    (ERR_LIB_PEM, 101),    // PEM_R_BAD_DECRYPT
+    (ERR_LIB_PKCS12, 113), // PKCS12_R_MAC_VERIFY_FAILURE
+    (ERR_LIB_PKCS12, 116), // PKCS12_R_PKCS12_CIPHERFINAL_ERROR
+    (ERR_LIB_PROV, 100),   // PROV_R_BAD_DECRYPT
+];
+
+/// The library and reason codes of an encryption scheme that is not
+/// available.
+const UNSUPPORTED_ENCRYPTION: &[(c_int, c_int)] = &[
+    (ERR_LIB_EVP, 107),    // EVP_R_UNSUPPORTED_CIPHER
+    (ERR_LIB_EVP, 123),    // EVP_R_UNSUPPORTED_KEYLENGTH
+    (ERR_LIB_EVP, 124),    // EVP_R_UNSUPPORTED_KEY_DERIVATION_FUNCTION
+    (ERR_LIB_EVP, 125),    // EVP_R_UNSUPPORTED_PRF
+    (ERR_LIB_EVP, 156),    // EVP_R_UNSUPPORTED_ALGORITHM
+    (ERR_LIB_EVP, 160),    // EVP_R_UNKNOWN_CIPHER
+    (ERR_LIB_PEM, 113),    // PEM_R_UNSUPPORTED_CIPHER
+    (ERR_LIB_PEM, 114),    // PEM_R_UNSUPPORTED_ENCRYPTION
+    (ERR_LIB_PKCS12, 118), // PKCS12_R_UNKNOWN_DIGEST_ALGORITHM
+    (ERR_LIB_PKCS12, 119), // PKCS12_R_UNSUPPORTED_PKCS12_MODE
+    (ERR_LIB_EVP, ERR_R_UNSUPPORTED),
+    (ERR_LIB_PKCS12, ERR_R_UNSUPPORTED),
+];
+
+/// Tell a wrong passphrase apart from an encryption scheme that is not
+/// available, such as the legacy PKCS #12 ciphers in OpenSSL 3, and from
+/// input that is not a valid encrypted key. Only a failed decryption or MAC
+/// check is reported as a wrong passphrase.
+///
+/// The errors are told apart by their library and reason codes, which do not
+/// change between OpenSSL releases, unlike the error strings. The mapping is
+/// tested with OpenSSL 3.5. OpenSSL 1.1 reports the same codes, except that
+/// it has no `ERR_R_UNSUPPORTED`. Libraries with other codes, such as
+/// BoringSSL, report every failure as `Error::InvalidKey`.
+fn decryption_error(error: ErrorStack) -> Error {
+    let has_code = |codes: &[(c_int, c_int)], error: &openssl::error::Error| {
+        codes.contains(&(error.library_code(), error.reason_code()))
+    };
+    let errors = error.errors();
+    if errors.iter().any(|error| has_code(WRONG_PASSPHRASE, error)) {
+        return Error::InvalidPassphrase;
+    }
+    match errors
+        .iter()
+        .find(|error| has_code(UNSUPPORTED_ENCRYPTION, error))
+    {
+        Some(error) => Error::UnsupportedKeyEncryption(error.to_string()),
+        None => Error::InvalidKey(format!("encrypted key could not be loaded: {}", error)),
+    }
+}
+
+fn unsupported_pem_label(label: &str, usage: &str) -> Error {
+    Error::InvalidKey(format!(
+        "PEM block {} can not be used as a {} key",
+        label, usage
+    ))
+}
+
+/// Implement the conversions from a JWK for a wrapper with either a public or
+/// a private key. The digest is chosen by the algorithm of the JWK.
+macro_rules! jwk_conversions {
+    ($key_type: ident, $key_from_jwk: ident) => {
+        impl TryFrom<&Jwk> for PKeyWithDigest<$key_type> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                let key = PKeyWithDigest::new($key_from_jwk(jwk)?, jwk_digest(jwk)?)?;
+                jwk.check_algorithm(&key.algorithm_type())?;
+                Ok(key)
+            }
+        }
+
+        impl TryFrom<&Jwk> for LowSPKeyWithDigest<$key_type> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                let key = LowSPKeyWithDigest::new($key_from_jwk(jwk)?, jwk_digest(jwk)?)?;
+                jwk.check_algorithm(&key.inner.algorithm_type())?;
+                Ok(key)
+            }
+        }
+
+        impl TryFrom<&Jwk> for PssPKeyWithDigest<$key_type> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                let key = PssPKeyWithDigest::new($key_from_jwk(jwk)?, jwk_digest(jwk)?)?;
+                jwk.check_algorithm(&key.algorithm_type())?;
+                Ok(key)
+            }
+        }
+
+        impl TryFrom<&Jwk> for EdDsaPKey<$key_type> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                jwk.check_algorithm(&AlgorithmType::EdDsa)?;
+                EdDsaPKey::new($key_from_jwk(jwk)?)
+            }
+        }
+    };
+}
+
+jwk_conversions!(Public, public_key_from_jwk);
+jwk_conversions!(Private, private_key_from_jwk);
+
+impl<T: HasPublic + HasParams> ToJwk for PKeyWithDigest<T> {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        let parameters = jwk_parameters(&self.key)?;
+        Ok(Jwk::signature_key(
+            parameters,
+            self.checked_algorithm_type()?,
+        ))
+    }
+}
+
+impl<T: HasPublic + HasParams> ToJwk for LowSPKeyWithDigest<T> {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        self.inner.to_jwk()
+    }
+}
+
+impl<T: HasPublic + HasParams> ToJwk for PssPKeyWithDigest<T> {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        let parameters = jwk_parameters(&self.key)?;
+        Ok(Jwk::signature_key(
+            parameters,
+            self.checked_algorithm_type()?,
+        ))
+    }
+}
+
+impl<T: HasPublic> ToJwk for EdDsaPKey<T> {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        let parameters = jwk_parameters(&self.key)?;
+        Ok(Jwk::signature_key(parameters, AlgorithmType::EdDsa))
+    }
+}
+
+/// The public key parameters of an RSA, EC or EdDSA key.
+fn jwk_parameters<T: HasPublic>(key: &PKeyRef<T>) -> Result<KeyParameters, Error> {
+    match key.id() {
+        Id::RSA | Id::RSA_PSS => {
+            let rsa = key.rsa()?;
+            Ok(KeyParameters::Rsa(RsaParameters::new(
+                rsa.n().to_vec(),
+                rsa.e().to_vec(),
+            )))
+        }
+        Id::EC => {
+            let ec_key = key.ec_key()?;
+            let group = ec_key.group();
+            let curve = match group.curve_name() {
+                Some(Nid::X9_62_PRIME256V1) => EllipticCurve::P256,
+                Some(Nid::SECP384R1) => EllipticCurve::P384,
+                Some(Nid::SECP521R1) => EllipticCurve::P521,
+                Some(Nid::SECP256K1) => EllipticCurve::Secp256k1,
+                curve => {
+                    return Err(Error::InvalidKey(format!(
+                        "EC curve {} has no JWK representation",
+                        curve.map_or("unknown", nid_name),
+                    )))
+                }
+            };
+
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            let mut context = BigNumContext::new()?;
+            ec_key
+                .public_key()
+                .affine_coordinates(group, &mut x, &mut y, &mut context)?;
+            let size = curve.coordinate_size() as i32;
+            Ok(KeyParameters::EllipticCurve(EllipticCurveParameters {
+                curve,
+                x_coordinate: x.to_vec_padded(size)?,
+                y_coordinate: y.to_vec_padded(size)?,
+                private_key: None,
+            }))
+        }
+        Id::ED25519 | Id::ED448 => {
+            let curve = if key.id() == Id::ED25519 {
+                OctetKeyPairCurve::Ed25519
+            } else {
+                OctetKeyPairCurve::Ed448
+            };
+            Ok(KeyParameters::OctetKeyPair(OctetKeyPairParameters {
+                curve,
+                public_key: key.raw_public_key()?,
+                private_key: None,
+            }))
+        }
+        id => Err(Error::InvalidKey(format!(
+            "key type {} has no JWK representation",
+            nid_name(Nid::from_raw(id.as_raw())),
+        ))),
+    }
+}
+
+/// The public and private parameters of an RSA, EC or EdDSA key.
+fn private_jwk_parameters(key: &PKeyRef<Private>) -> Result<KeyParameters, Error> {
+    let mut parameters = jwk_parameters(key)?;
+    match parameters {
+        KeyParameters::Rsa(ref mut parameters) => {
+            let rsa = key.rsa()?;
+            let component = |value: Option<&BigNumRef>| value.map(BigNumRef::to_vec);
+            parameters.private_exponent = Some(rsa.d().to_vec());
+            parameters.first_prime = component(rsa.p());
+            parameters.second_prime = component(rsa.q());
+            parameters.first_prime_exponent = component(rsa.dmp1());
+            parameters.second_prime_exponent = component(rsa.dmq1());
+            parameters.coefficient = component(rsa.iqmp());
+        }
+        KeyParameters::EllipticCurve(ref mut parameters) => {
+            let size = parameters.curve.coordinate_size() as i32;
+            parameters.private_key = Some(key.ec_key()?.private_key().to_vec_padded(size)?);
+        }
+        KeyParameters::OctetKeyPair(ref mut parameters) => {
+            parameters.private_key = Some(key.raw_private_key()?);
+        }
+        KeyParameters::OctetSequence(_) => return Err(symmetric_key()),
+    }
+    Ok(parameters)
+}
+
+fn jwk_digest(jwk: &Jwk) -> Result<MessageDigest, Error> {
+    algorithm_digest(&jwk.algorithm_type()?)
+}
+
+fn algorithm_digest(algorithm: &AlgorithmType) -> Result<MessageDigest, Error> {
+    use AlgorithmType::*;
+
+    match *algorithm {
+        Rs256 | Ps256 | Es256 | Es256k => Ok(MessageDigest::sha256()),
+        Rs384 | Ps384 | Es384 => Ok(MessageDigest::sha384()),
+        Rs512 | Ps512 | Es512 => Ok(MessageDigest::sha512()),
+        ref other => Err(Error::UnsupportedAlgorithm(other.clone())),
+    }
+}
+
+fn public_key_from_jwk(jwk: &Jwk) -> Result<PKey<Public>, Error> {
+    match jwk.parameters {
+        KeyParameters::Rsa(ref parameters) => {
+            let rsa = Rsa::from_public_components(
+                BigNum::from_slice(&parameters.modulus)?,
+                BigNum::from_slice(&parameters.exponent)?,
+            )?;
+            Ok(PKey::from_rsa(rsa)?)
+        }
+        KeyParameters::EllipticCurve(_) => Ok(PKey::from_ec_key(ec_public_key_from_jwk(jwk)?)?),
+        KeyParameters::OctetKeyPair(ref parameters) => Ok(PKey::public_key_from_raw_bytes(
+            &parameters.public_key,
+            okp_id(parameters.curve)?,
+        )?),
+        KeyParameters::OctetSequence(_) => Err(symmetric_key()),
+    }
+}
+
+fn private_key_from_jwk(jwk: &Jwk) -> Result<PKey<Private>, Error> {
+    match jwk.parameters {
+        KeyParameters::Rsa(ref parameters) => {
+            let component = |parameter: &Option<Vec<u8>>, name: &str| {
+                Ok::<_, Error>(BigNum::from_slice(private_parameter(parameter, name)?)?)
+            };
+            let rsa = Rsa::from_private_components(
+                BigNum::from_slice(&parameters.modulus)?,
+                BigNum::from_slice(&parameters.exponent)?,
+                component(&parameters.private_exponent, "d")?,
+                component(&parameters.first_prime, "p")?,
+                component(&parameters.second_prime, "q")?,
+                component(&parameters.first_prime_exponent, "dp")?,
+                component(&parameters.second_prime_exponent, "dq")?,
+                component(&parameters.coefficient, "qi")?,
+            )?;
+            if !rsa.check_key()? {
+                return Err(mismatched_private_key());
+            }
+            Ok(PKey::from_rsa(rsa)?)
+        }
+        KeyParameters::EllipticCurve(ref parameters) => {
+            let public_key = ec_public_key_from_jwk(jwk)?;
+            let private_key = BigNum::from_slice(private_parameter(&parameters.private_key, "d")?)?;
+            let ec_key = EcKey::from_private_components(
+                public_key.group(),
+                &private_key,
+                public_key.public_key(),
+            )?;
+            ec_key.check_key().map_err(|_| mismatched_private_key())?;
+            Ok(PKey::from_ec_key(ec_key)?)
+        }
+        KeyParameters::OctetKeyPair(ref parameters) => {
+            let private_key = private_parameter(&parameters.private_key, "d")?;
+            let key = PKey::private_key_from_raw_bytes(private_key, okp_id(parameters.curve)?)?;
+            if key.raw_public_key()? != parameters.public_key {
+                return Err(mismatched_private_key());
+            }
+            Ok(key)
+        }
+        KeyParameters::OctetSequence(_) => Err(symmetric_key()),
+    }
+}
+
+fn ec_public_key_from_jwk(jwk: &Jwk) -> Result<EcKey<Public>, Error> {
+    let parameters = jwk.elliptic_curve_parameters()?;
+    let curve = match parameters.curve {
+        EllipticCurve::P256 => Nid::X9_62_PRIME256V1,
+        EllipticCurve::P384 => Nid::SECP384R1,
+        EllipticCurve::P521 => Nid::SECP521R1,
+        EllipticCurve::Secp256k1 => Nid::SECP256K1,
+    };
+    let group = EcGroup::from_curve_name(curve)?;
+    let x = BigNum::from_slice(&parameters.x_coordinate)?;
+    let y = BigNum::from_slice(&parameters.y_coordinate)?;
+    Ok(EcKey::from_public_key_affine_coordinates(&group, &x, &y)?)
+}
+
+fn okp_id(curve: OctetKeyPairCurve) -> Result<Id, Error> {
+    match curve {
+        OctetKeyPairCurve::Ed25519 => Ok(Id::ED25519),
+        OctetKeyPairCurve::Ed448 => Ok(Id::ED448),
+        curve => Err(Error::InvalidKey(format!(
+            "OKP curve {:?} can not be used for signatures",
+            curve
+        ))),
+    }
+}
+
+fn symmetric_key() -> Error {
+    Error::InvalidKey("symmetric keys are not supported by the OpenSSL backend".to_owned())
+}
+
+fn mismatched_private_key() -> Error {
+    Error::InvalidKey("the private key does not match the public key".to_owned())
+}
+
+/// Extract the public half of a private key.
+fn public_key(key: &PKeyRef<Private>) -> Result<PKey<Public>, Error> {
+    Ok(PKey::public_key_from_der(&key.public_key_to_der()?)?)
+}
+
+/// EdDSA can not be computed incrementally, so the whole input is needed at once.
+fn signing_input(header: &str, claims: &str) -> String {
+    [header, claims].join(SEPARATOR)
+}
+
+/// The smallest RSA modulus accepted by the checked constructors, following
+/// [RFC 7518](https://tools.ietf.org/html/rfc7518#section-3.3).
+const MIN_RSA_BITS: u32 = 2048;
+
+fn check_rsa_key_size<T: HasPublic>(key: &PKeyRef<T>) -> Result<(), Error> {
+    let bits = key.bits();
+    if (key.id() == Id::RSA || key.id() == Id::RSA_PSS) && bits < MIN_RSA_BITS {
+        return Err(Error::InvalidKey(format!(
+            "RSA key has {} bits but at least {} are required",
+            bits, MIN_RSA_BITS
+        )));
+    }
+    Ok(())
+}
+
+/// The algorithm type of wrappers that were built from their public fields
+/// instead of a checked constructor, and whose key and digest do not form a
+/// supported algorithm. A token header can name the same algorithm, so
+/// signing and verifying do not rely on it: they check the key and digest
+/// again and fail with `Error::InvalidKey`.
+fn invalid_algorithm_type() -> AlgorithmType {
+    AlgorithmType::Other("invalid".to_owned())
+}
+
+fn nid_name(nid: Nid) -> &'static str {
+    nid.short_name().unwrap_or("unknown")
+}
+
+fn sign<T: HasPrivate + HasParams>(
+    mut signer: Signer,
+    key: &PKeyRef<T>,
+    header: &str,
+    claims: &str,
+    low_s: bool,
+) -> Result<Vec<u8>, Error> {
+    signer.update(header.as_bytes())?;
+    signer.update(SEPARATOR.as_bytes())?;
+    signer.update(claims.as_bytes())?;
+    let signer_signature = signer.sign_to_vec()?;
+
+    if key.id() == Id::EC {
+        let ec_key = key.ec_key()?;
+        der_to_jose(ec_key.group(), &signer_signature, low_s)
+    } else {
+        Ok(signer_signature)
+    }
+}
+
+fn verify<T: HasPublic + HasParams>(
+    mut verifier: Verifier,
+    key: &PKeyRef<T>,
+    header: &str,
+    claims: &str,
+    signature: &[u8],
+    low_s: bool,
+) -> Result<bool, Error> {
+    verifier.update(header.as_bytes())?;
+    verifier.update(SEPARATOR.as_bytes())?;
+    verifier.update(claims.as_bytes())?;
+
+    let verified = if key.id() == Id::EC {
+        let ec_key = key.ec_key()?;
+        let group = ec_key.group();
+        let ecdsa_signature = jose_to_ecdsa(group, signature)?;
+        if low_s && is_high_s(group, ecdsa_signature.s())? {
+            return Ok(false);
+        }
+        verifier.verify(&ecdsa_signature.to_der()?)?
+    } else {
+        verifier.verify(signature)?
+    };
+
+    Ok(verified)
+}
+
+/// The size of each of R and S in a JOSE ECDSA signature, which is the size
+/// of a coordinate on the curve: 32 bytes for P-256, 48 for P-384 and 66 for
+/// P-521.
+fn ecdsa_component_size(group: &EcGroupRef) -> usize {
+    (group.degree() as usize + 7) / 8
+}
+
+fn group_order(group: &EcGroupRef) -> Result<BigNum, Error> {
+    let mut order = BigNum::new()?;
+    let mut context = BigNumContext::new()?;
+    group.order(&mut order, &mut context)?;
+    Ok(order)
+}
+
+fn is_high_s(group: &EcGroupRef, s: &BigNumRef) -> Result<bool, Error> {
+    let order = group_order(group)?;
+    let mut half_order = BigNum::new()?;
+    half_order.rshift1(&order)?;
+    Ok(s > &*half_order)
+}
+
+/// OpenSSL by default signs ECDSA in DER, but JOSE expects them in a concatenated (R, S) format
+/// where both are left padded to the size of the curve.
+fn der_to_jose(group: &EcGroupRef, der: &[u8], low_s: bool) -> Result<Vec<u8>, Error> {
+    let signature = EcdsaSig::from_der(der)?;
+    let size = ecdsa_component_size(group) as i32;
+
+    let mut s = signature.s().to_owned()?;
+    if low_s && is_high_s(group, &s)? {
+        s = &*group_order(group)? - &*s;
+    }
+
+    let mut jose = signature.r().to_vec_padded(size)?;
+    jose.extend(s.to_vec_padded(size)?);
+    Ok(jose)
+}
+
+/// OpenSSL by default verifies ECDSA in DER, but JOSE parses out a concatenated (R, S) format.
+/// Signatures that do not have exactly the size expected for the curve are rejected.
+fn jose_to_ecdsa(group: &EcGroupRef, jose: &[u8]) -> Result<EcdsaSig, Error> {
+    let size = ecdsa_component_size(group);
+    if jose.len() != 2 * size {
+        return Err(Error::InvalidSignature);
+    }
+
+    let (r, s) = jose.split_at(size);
+    let ecdsa_signature =
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
+    Ok(ecdsa_signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::openssl::{
+        group_order, EdDsaPKey, LowSPKeyWithDigest, PKeyWithDigest, PssPKeyWithDigest,
+    };
+    use crate::algorithm::AlgorithmType::*;
+    use crate::algorithm::{SigningAlgorithm, VerifyingAlgorithm};
+    use crate::error::Error;
+    use crate::header::PrecomputedAlgorithmOnlyHeader as AlgOnly;
+    use crate::jwk::{Jwk, KeyParameters};
+    use crate::ToBase64;
+
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private, Public};
+    use openssl::rsa::Rsa;
+
+    // {"sub":"1234567890","name":"John Doe","admin":true}
+    const CLAIMS: &str = "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+
+    trait Algorithm {
+        fn signing(&self) -> &(dyn SigningAlgorithm + 'static);
+        fn verifying(&self) -> &(dyn VerifyingAlgorithm + 'static);
+    }
+
+    impl<T: SigningAlgorithm + VerifyingAlgorithm + 'static> Algorithm for T {
+        fn signing(&self) -> &(dyn SigningAlgorithm + 'static) {
+            self
+        }
+
+        fn verifying(&self) -> &(dyn VerifyingAlgorithm + 'static) {
+            self
+        }
+    }
+
+    impl AsRef<dyn SigningAlgorithm> for Box<dyn Algorithm> {
+        fn as_ref(&self) -> &(dyn SigningAlgorithm + 'static) {
+            (**self).signing()
+        }
+    }
+
+    impl AsRef<dyn VerifyingAlgorithm> for Box<dyn Algorithm> {
+        fn as_ref(&self) -> &(dyn VerifyingAlgorithm + 'static) {
+            (**self).verifying()
+        }
+    }
+
+    const RS256_SIGNATURE: &str =
+    "cQsAHF2jHvPGFP5zTD8BgoJrnzEx6JNQCpupebWLFnOc2r_punDDTylI6Ia4JZNkvy2dQP-7W-DEbFQ3oaarHsDndqUgwf9iYlDQxz4Rr2nEZX1FX0-FMEgFPeQpdwveCgjtTYUbVy37ijUySN_rW-xZTrsh_Ug-ica8t-zHRIw";
+
+    #[test]
+    fn rs256_sign() -> Result<(), Error> {
+        let pem = include_bytes!("../../test/rs256-private.pem");
+
+        let algorithm = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::private_key_from_pem(pem)?,
+        };
+
+        let result = algorithm.sign(&AlgOnly(Rs256).to_base64()?, CLAIMS)?;
+        assert_eq!(result, RS256_SIGNATURE);
+        Ok(())
+    }
+
+    #[test]
+    fn rs256_verify() -> Result<(), Error> {
+        let pem = include_bytes!("../../test/rs256-public.pem");
+
+        let algorithm = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::public_key_from_pem(pem)?,
+        };
+
+        let verification_result =
+            algorithm.verify(&AlgOnly(Rs256).to_base64()?, CLAIMS, RS256_SIGNATURE)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    const PS256_SIGNATURE: &str =
+    "ZVQDoU3dZqeJFLRunR02y1aTCGRmFweeGfY-bbS9_oTS10jqEEGfAtANu0moQQJzi-m3ydfS2W5yEynln_3qYpaljTibeMj6DYwq7OJgyBI900L3DVWD5G0MbPfQ2IloN7rbews9ALAvHzJ0c6I5VPRfIJrg3Fwi-09ltY84uPSd4w4LSHyUfq8v2v_4qNQJYtlBeK1ZkbzmAaA6E5nVSe3m2SLlZA61d4f8M-n6kBbhVF7R5dK03omMiaIIQchhRhRnlk4Su9_uDORE2LtC0VK0pa-HmVAKpjmY3OaGLHknuT26O-_uGg3x1k9-fdi2FSEYSSrt92ok6LgFfgkHBQ";
+
+    #[test]
+    fn ps256_verify() -> Result<(), Error> {
+        let pem = include_bytes!("../../test/rs2048-public.pem");
+
+        let algorithm = PssPKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::public_key_from_pem(pem)?,
+        };
+
+        let verification_result =
+            algorithm.verify(&AlgOnly(Ps256).to_base64()?, CLAIMS, PS256_SIGNATURE)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn ps256_does_not_verify_pkcs1() -> Result<(), Error> {
+        let private_pem = include_bytes!("../../test/rs2048-private.pem");
+        let private_key = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::private_key_from_pem(private_pem)?,
+        };
+
+        let signature = private_key.sign(&AlgOnly(Ps256).to_base64()?, CLAIMS)?;
+
+        let public_pem = include_bytes!("../../test/rs2048-public.pem");
+        let public_key = PssPKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::public_key_from_pem(public_pem)?,
+        };
+
+        let verification_result =
+            public_key.verify(&AlgOnly(Ps256).to_base64()?, CLAIMS, &signature)?;
+        assert!(!verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn ps_roundtrip() -> Result<(), Error> {
+        let private_pem = include_bytes!("../../test/rs2048-private.pem");
+        let public_pem = include_bytes!("../../test/rs2048-public.pem");
+
+        let algorithms = [
+            (MessageDigest::sha256(), Ps256),
+            (MessageDigest::sha384(), Ps384),
+            (MessageDigest::sha512(), Ps512),
+        ];
+
+        for (digest, algorithm_type) in algorithms.iter() {
+            let private_key = PssPKeyWithDigest {
+                digest: *digest,
+                key: PKey::private_key_from_pem(private_pem)?,
+            };
+            let public_key = PssPKeyWithDigest {
+                digest: *digest,
+                key: PKey::public_key_from_pem(public_pem)?,
+            };
+            assert_eq!(
+                SigningAlgorithm::algorithm_type(&private_key),
+                *algorithm_type
+            );
+            assert_eq!(
+                VerifyingAlgorithm::algorithm_type(&public_key),
+                *algorithm_type
+            );
+
+            let header = AlgOnly(algorithm_type.clone());
+            let header = header.to_base64()?;
+            let signature = private_key.sign(&header, CLAIMS)?;
+            assert!(public_key.verify(&header, CLAIMS, &signature)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn checked_constructors() -> Result<(), Error> {
+        let rs2048_pem = include_bytes!("../../test/rs2048-public.pem");
+        let es384_pem = include_bytes!("../../test/es384-public.pem");
+        let ed25519_pem = include_bytes!("../../test/ed25519-public.pem");
+
+        let key = PKeyWithDigest::new(
+            PKey::public_key_from_pem(rs2048_pem)?,
+            MessageDigest::sha384(),
+        )?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&key), Rs384);
+
+        let key = PKeyWithDigest::new(
+            PKey::public_key_from_pem(es384_pem)?,
+            MessageDigest::sha384(),
+        )?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&key), Es384);
+
+        let key = PssPKeyWithDigest::new(
+            PKey::public_key_from_pem(rs2048_pem)?,
+            MessageDigest::sha512(),
+        )?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&key), Ps512);
+
+        let key = EdDsaPKey::new(PKey::public_key_from_pem(ed25519_pem)?)?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&key), EdDsa);
+        Ok(())
+    }
+
+    #[test]
+    fn checked_constructors_reject_invalid_keys() -> Result<(), Error> {
+        fn assert_invalid<T>(result: Result<T, Error>) {
+            match result {
+                Err(Error::InvalidKey(_)) => (),
+                Err(other) => panic!("Wrong error type: {:?}", other),
+                Ok(_) => panic!("Invalid key should have been rejected"),
+            }
+        }
+
+        let rs1024_pem = include_bytes!("../../test/rs256-public.pem");
+        let es256_pem = include_bytes!("../../test/es256-public.pem");
+        let es384_pem = include_bytes!("../../test/es384-public.pem");
+        let ed25519_pem = include_bytes!("../../test/ed25519-public.pem");
+
+        // RSA keys under 2048 bits
+        assert_invalid(PKeyWithDigest::new(
+            PKey::public_key_from_pem(rs1024_pem)?,
+            MessageDigest::sha256(),
+        ));
+        assert_invalid(PssPKeyWithDigest::new(
+            PKey::public_key_from_pem(rs1024_pem)?,
+            MessageDigest::sha256(),
+        ));
+
+        // Curves that do not match the digest
+        assert_invalid(PKeyWithDigest::new(
+            PKey::public_key_from_pem(es384_pem)?,
+            MessageDigest::sha256(),
+        ));
+        assert_invalid(PKeyWithDigest::new(
+            PKey::public_key_from_pem(es256_pem)?,
+            MessageDigest::sha512(),
+        ));
+
+        // Key types that do not match the wrapper
+        assert_invalid(PKeyWithDigest::new(
+            PKey::public_key_from_pem(ed25519_pem)?,
+            MessageDigest::sha256(),
+        ));
+        assert_invalid(PssPKeyWithDigest::new(
+            PKey::public_key_from_pem(es256_pem)?,
+            MessageDigest::sha256(),
+        ));
+        assert_invalid(EdDsaPKey::new(PKey::public_key_from_pem(es256_pem)?));
+
+        // Unsupported digests
+        assert_invalid(PKeyWithDigest::new(
+            PKey::public_key_from_pem(include_bytes!("../../test/rs2048-public.pem"))?,
+            MessageDigest::sha1(),
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unchecked_invalid_keys() -> Result<(), Error> {
+        use crate::token::signed::SignWithKey;
+        use crate::token::verified::VerifyWithKey;
+
+        let private_key = PKeyWithDigest {
+            digest: MessageDigest::sha1(),
+            key: PKey::private_key_from_pem(include_bytes!("../../test/rs2048-private.pem"))?,
+        };
+        assert_eq!(
+            SigningAlgorithm::algorithm_type(&private_key),
+            Other("invalid".to_owned())
+        );
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        match claims.sign_with_key(&private_key) {
+            Err(Error::InvalidKey(_)) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let public_key = EdDsaPKey {
+            key: PKey::public_key_from_pem(include_bytes!("../../test/es256-public.pem"))?,
+        };
+        let token = "eyJhbGciOiJFZERTQSJ9.e30.c2lnbmF0dXJl";
+        let result: Result<BTreeMap<String, String>, _> = token.verify_with_key(&public_key);
+        match result {
+            Err(Error::AlgorithmMismatch(EdDsa, Other(_))) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        // A header naming the reported algorithm is still rejected.
+        let public_key = PKeyWithDigest {
+            digest: MessageDigest::sha1(),
+            key: PKey::public_key_from_pem(include_bytes!("../../test/rs2048-public.pem"))?,
+        };
+        let token = "eyJhbGciOiJpbnZhbGlkIn0.e30.c2lnbmF0dXJl";
+        let result: Result<BTreeMap<String, String>, _> = token.verify_with_key(&public_key);
+        match result {
+            Err(Error::InvalidKey(_)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn es256k() -> Result<(), Error> {
+        let private_pem = include_bytes!("../../test/es256k-private.pem");
+        let private_key = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::private_key_from_pem(private_pem)?,
+        };
+
+        let signature = private_key.sign(&AlgOnly(Es256k).to_base64()?, CLAIMS)?;
+
+        let public_pem = include_bytes!("../../test/es256k-public.pem");
+        let public_key = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::public_key_from_pem(public_pem)?,
+        };
+
+        assert_eq!(SigningAlgorithm::algorithm_type(&private_key), Es256k);
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&public_key), Es256k);
+        let verification_result =
+            public_key.verify(&AlgOnly(Es256k).to_base64()?, CLAIMS, &signature)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn es256() -> Result<(), Error> {
+        let private_pem = include_bytes!("../../test/es256-private.pem");
+        let private_key = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::private_key_from_pem(private_pem)?,
+        };
+
+        let signature = private_key.sign(&AlgOnly(Es256).to_base64()?, CLAIMS)?;
+
+        let public_pem = include_bytes!("../../test/es256-public.pem");
+
+        let public_key = PKeyWithDigest {
+            digest: MessageDigest::sha256(),
+            key: PKey::public_key_from_pem(public_pem)?,
+        };
+
+        let verification_result =
+            public_key.verify(&AlgOnly(Es256).to_base64()?, CLAIMS, &signature)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    // Signed by another implementation, with a leading zero byte in R
+    const ES256_SIGNATURE: &str =
+        "AGafEbu80OpWMQ2OXIF4uFod28FDDaS33Ss6Ux606SXIltRQkjaY2tuXYaCgQDUKorhKI_s31ed3O9YQPt9jDQ";
+    const ES384_SIGNATURE: &str = "AI2rj5nTzZ8E2LnSXoFBBQn1SB8Ijzc5udCLCidC_S5gb3R0pZRuhtxA41vmqIs95n1n7XvhvkVFDofWMTMGRHi-_NoqACLNTLS7Io1V3UIKMYBH45-3e7Ntc3HzivqD";
+
+    fn ec_keys(
+        name: &str,
+        digest: MessageDigest,
+    ) -> Result<(PKeyWithDigest<Private>, PKeyWithDigest<Public>), Error> {
+        let private_pem = std::fs::read(format!("test/{}-private.pem", name)).unwrap();
+        let public_pem = std::fs::read(format!("test/{}-public.pem", name)).unwrap();
+        let private_key = PKeyWithDigest::new(PKey::private_key_from_pem(&private_pem)?, digest)?;
+        let public_key = PKeyWithDigest::new(PKey::public_key_from_pem(&public_pem)?, digest)?;
+        Ok((private_key, public_key))
+    }
+
+    #[test]
+    fn ecdsa_verify_leading_zero() -> Result<(), Error> {
+        let (_, es256_key) = ec_keys("es256", MessageDigest::sha256())?;
+        assert!(es256_key.verify(&AlgOnly(Es256).to_base64()?, CLAIMS, ES256_SIGNATURE)?);
+
+        let (_, es384_key) = ec_keys("es384", MessageDigest::sha384())?;
+        assert!(es384_key.verify(&AlgOnly(Es384).to_base64()?, CLAIMS, ES384_SIGNATURE)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ecdsa_signatures_have_fixed_width() -> Result<(), Error> {
+        let keys = [
+            ("es256", MessageDigest::sha256(), 64),
+            ("es256k", MessageDigest::sha256(), 64),
+            ("es384", MessageDigest::sha384(), 96),
+            ("es512", MessageDigest::sha512(), 132),
+        ];
+
+        for (name, digest, length) in keys.iter() {
+            let (private_key, public_key) = ec_keys(name, *digest)?;
+            let header = AlgOnly(SigningAlgorithm::algorithm_type(&private_key));
+            let header = header.to_base64()?;
+
+            // Roughly 1 in 128 signatures has a leading zero byte in R or S
+            for _ in 0..1024 {
+                let signature = private_key.sign(&header, CLAIMS)?;
+                let signature_bytes = base64::decode_config(&signature, base64::URL_SAFE_NO_PAD)?;
+                assert_eq!(signature_bytes.len(), *length, "{}", name);
+                assert!(public_key.verify(&header, CLAIMS, &signature)?, "{}", name);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ecdsa_wrong_signature_length() -> Result<(), Error> {
+        let (_, public_key) = ec_keys("es256", MessageDigest::sha256())?;
+        let header = AlgOnly(Es256).to_base64()?;
+        let signature = base64::decode_config(ES256_SIGNATURE, base64::URL_SAFE_NO_PAD)?;
+
+        // The same signature without the leading zero byte of R
+        for signature in [&signature[1..], &[&signature[..], &[0]].concat()[..]].iter() {
+            match public_key.verify_bytes(&header, CLAIMS, signature) {
+                Err(Error::InvalidSignature) => (),
+                other => panic!("Wrong result: {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ecdsa_low_s() -> Result<(), Error> {
+        use openssl::bn::BigNum;
+        use openssl::ecdsa::EcdsaSig;
+
+        let private_pem = include_bytes!("../../test/es256-private.pem");
+        let public_pem = include_bytes!("../../test/es256-public.pem");
+        let private_key = LowSPKeyWithDigest::new(
+            PKey::private_key_from_pem(private_pem)?,
+            MessageDigest::sha256(),
+        )?;
+        let public_key = LowSPKeyWithDigest::new(
+            PKey::public_key_from_pem(public_pem)?,
+            MessageDigest::sha256(),
+        )?;
+        let (_, lenient_public_key) = ec_keys("es256", MessageDigest::sha256())?;
+        let header = AlgOnly(Es256).to_base64()?;
+
+        let ec_key = public_key.key().key.ec_key()?;
+        let order = group_order(ec_key.group())?;
+        let mut half_order = BigNum::new()?;
+        half_order.rshift1(&order)?;
+
+        for _ in 0..64 {
+            let signature = private_key.sign(&header, CLAIMS)?;
+            let signature_bytes = base64::decode_config(&signature, base64::URL_SAFE_NO_PAD)?;
+            assert_eq!(signature_bytes.len(), 64);
+            let s = BigNum::from_slice(&signature_bytes[32..])?;
+            assert!(s <= half_order);
+            assert!(public_key.verify(&header, CLAIMS, &signature)?);
+
+            // The high-S form of the same signature
+            let high_s = &*order - &*s;
+            let high_s_signature = EcdsaSig::from_private_components(
+                BigNum::from_slice(&signature_bytes[..32])?,
+                high_s,
+            )?;
+            let high_s_bytes = [
+                high_s_signature.r().to_vec_padded(32)?,
+                high_s_signature.s().to_vec_padded(32)?,
+            ]
+            .concat();
+            assert!(lenient_public_key.verify_bytes(&header, CLAIMS, &high_s_bytes)?);
+            assert!(!public_key.verify_bytes(&header, CLAIMS, &high_s_bytes)?);
+        }
+
+        let rsa_pem = include_bytes!("../../test/rs2048-public.pem");
+        assert!(LowSPKeyWithDigest::new(
+            PKey::public_key_from_pem(rsa_pem)?,
+            MessageDigest::sha256()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn key_pairs() -> Result<(), Error> {
+        use crate::algorithm::KeyPair;
+        use crate::token::signed::SignWithStore;
+        use crate::token::verified::VerifyWithStore;
+        use std::collections::BTreeMap;
+
+        let rs2048_pem = include_bytes!("../../test/rs2048-private.pem");
+        let es384_pem = include_bytes!("../../test/es384-private.pem");
+        let ed25519_pem = include_bytes!("../../test/ed25519-private.pem");
+
+        let mut store: BTreeMap<&str, Box<dyn Algorithm>> = BTreeMap::new();
+        store.insert(
+            "rs256",
+            Box::new(KeyPair::<PKeyWithDigest<_>, _>::from_private_key(
+                PKey::private_key_from_pem(rs2048_pem)?,
+                MessageDigest::sha256(),
+            )?),
+        );
+        store.insert(
+            "es384",
+            Box::new(KeyPair::<PKeyWithDigest<_>, _>::from_private_key(
+                PKey::private_key_from_pem(es384_pem)?,
+                MessageDigest::sha384(),
+            )?),
+        );
+        store.insert(
+            "es384-low-s",
+            Box::new(KeyPair::<LowSPKeyWithDigest<_>, _>::from_private_key(
+                PKey::private_key_from_pem(es384_pem)?,
+                MessageDigest::sha384(),
+            )?),
+        );
+        store.insert(
+            "ps512",
+            Box::new(KeyPair::<PssPKeyWithDigest<_>, _>::from_private_key(
+                PKey::private_key_from_pem(rs2048_pem)?,
+                MessageDigest::sha512(),
+            )?),
+        );
+        store.insert(
+            "ed25519",
+            Box::new(KeyPair::<EdDsaPKey<_>, _>::from_private_key(
+                PKey::private_key_from_pem(ed25519_pem)?,
+            )?),
+        );
+
+        for key_id in store.keys() {
+            let mut claims = BTreeMap::new();
+            claims.insert("sub", "someone");
+            let token_str = (*key_id, claims).sign_with_store(&store)?;
+            let claims: BTreeMap<String, String> = token_str.as_str().verify_with_store(&store)?;
+            assert_eq!(claims["sub"], "someone");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn key_pair_checks_key() -> Result<(), Error> {
+        use crate::algorithm::KeyPair;
+
+        let es256_pem = include_bytes!("../../test/es256-private.pem");
+        let result = KeyPair::<PKeyWithDigest<_>, _>::from_private_key(
+            PKey::private_key_from_pem(es256_pem)?,
+            MessageDigest::sha384(),
+        );
+        match result {
+            Err(Error::InvalidKey(_)) => Ok(()),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Invalid key should have been rejected"),
+        }
+    }
+
+    // RFC 8037 Appendix A.4
+    const ED25519_HEADER: &str = "eyJhbGciOiJFZERTQSJ9";
+    const ED25519_PAYLOAD: &str = "RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc";
+    const ED25519_SIGNATURE: &str =
+        "hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+
+    #[test]
+    fn ed25519_sign() -> Result<(), Error> {
+        let pem = include_bytes!("../../test/ed25519-private.pem");
+        let algorithm = EdDsaPKey {
+            key: PKey::private_key_from_pem(pem)?,
+        };
+
+        let result = algorithm.sign(ED25519_HEADER, ED25519_PAYLOAD)?;
+        assert_eq!(result, ED25519_SIGNATURE);
+        Ok(())
+    }
+
+    #[test]
+    fn ed25519_verify() -> Result<(), Error> {
+        let pem = include_bytes!("../../test/ed25519-public.pem");
+        let algorithm = EdDsaPKey {
+            key: PKey::public_key_from_pem(pem)?,
+        };
+
+        assert!(algorithm.verify(ED25519_HEADER, ED25519_PAYLOAD, ED25519_SIGNATURE)?);
+        assert!(!algorithm.verify(ED25519_HEADER, CLAIMS, ED25519_SIGNATURE)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ed448() -> Result<(), Error> {
+        let private_pem = include_bytes!("../../test/ed448-private.pem");
+        let private_key = EdDsaPKey {
+            key: PKey::private_key_from_pem(private_pem)?,
+        };
+
+        let signature = private_key.sign(&AlgOnly(EdDsa).to_base64()?, CLAIMS)?;
+
+        let public_pem = include_bytes!("../../test/ed448-public.pem");
+        let public_key = EdDsaPKey {
+            key: PKey::public_key_from_pem(public_pem)?,
+        };
+
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&public_key), EdDsa);
+        let verification_result =
+            public_key.verify(&AlgOnly(EdDsa).to_base64()?, CLAIMS, &signature)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    fn jwk(name: &str, kind: &str) -> Jwk {
+        let json = std::fs::read_to_string(format!("test/{}-{}.jwk", name, kind)).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn jwk_conversions() -> Result<(), Error> {
+        let fixtures = [
+            ("rs2048", Rs256, MessageDigest::sha256()),
+            ("es256", Es256, MessageDigest::sha256()),
+            ("es256k", Es256k, MessageDigest::sha256()),
+            ("es384", Es384, MessageDigest::sha384()),
+            ("es512", Es512, MessageDigest::sha512()),
+        ];
+
+        for (name, algorithm, digest) in fixtures.iter() {
+            let mut private_jwk = jwk(name, "private");
+            let mut public_jwk = jwk(name, "public");
+            private_jwk.algorithm = Some(algorithm.clone());
+            public_jwk.algorithm = Some(algorithm.clone());
+            let signing_key = PKeyWithDigest::<Private>::try_from(&private_jwk)?;
+            let verifying_key = PKeyWithDigest::<Public>::try_from(&public_jwk)?;
+
+            // The converted keys are the keys of the PEM files
+            let public_pem = std::fs::read(format!("test/{}-public.pem", name)).unwrap();
+            let pem_key = PKeyWithDigest::new(PKey::public_key_from_pem(&public_pem)?, *digest)?;
+            assert!(verifying_key.key.public_eq(&pem_key.key));
+
+            let header = AlgOnly(algorithm.clone()).to_base64()?.into_owned();
+            let signature = signing_key.sign(&header, CLAIMS)?;
+            assert!(pem_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        let mut ps384_jwk = jwk("rs2048", "private");
+        ps384_jwk.algorithm = Some(Ps384);
+        let ps384_key = PssPKeyWithDigest::<Private>::try_from(&ps384_jwk)?;
+        assert_eq!(SigningAlgorithm::algorithm_type(&ps384_key), Ps384);
+        let low_s_key = LowSPKeyWithDigest::<Public>::try_from(&jwk("es256", "public"))?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&low_s_key), Es256);
+
+        for name in ["ed25519", "ed448"].iter() {
+            let signing_key = EdDsaPKey::<Private>::try_from(&jwk(name, "private"))?;
+            let verifying_key = EdDsaPKey::<Public>::try_from(&jwk(name, "public"))?;
+            let header = AlgOnly(EdDsa).to_base64()?;
+            let signature = signing_key.sign(&header, CLAIMS)?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn jwk_conversions_check_key() {
+        let mut rs256 = jwk("rs2048", "public");
+        rs256.algorithm = Some(Rs256);
+        match PssPKeyWithDigest::<Public>::try_from(&rs256) {
+            Err(Error::AlgorithmMismatch(Rs256, Ps256)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("The alg of the JWK should be respected"),
+        }
+
+        match EdDsaPKey::<Public>::try_from(&jwk("es256", "public")) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("An EC key can not be used for EdDSA"),
+        }
+
+        match PKeyWithDigest::<Private>::try_from(&jwk("es256", "public")) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("A public JWK has no private key"),
+        }
+
+        let mut mismatched = jwk("es256", "private");
+        if let KeyParameters::EllipticCurve(ref mut parameters) = mismatched.parameters {
+            parameters.private_key.as_mut().unwrap()[31] ^= 1;
+        }
+        match PKeyWithDigest::<Private>::try_from(&mismatched) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Private key does not match the public key"),
+        }
+
+        let mut mismatched = jwk("ed25519", "private");
+        if let KeyParameters::OctetKeyPair(ref mut parameters) = mismatched.parameters {
+            parameters.private_key.as_mut().unwrap()[0] ^= 1;
+        }
+        match EdDsaPKey::<Private>::try_from(&mismatched) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Private key does not match the public key"),
+        }
+    }
+
+    #[test]
+    fn jwk_export() -> Result<(), Error> {
+        use crate::jwk::{JwkSet, KeyUse, ToJwk};
+        use std::collections::BTreeMap;
+
+        let fixtures = [
+            ("rs2048", MessageDigest::sha256(), Rs256),
+            ("es256", MessageDigest::sha256(), Es256),
+            ("es256k", MessageDigest::sha256(), Es256k),
+            ("es384", MessageDigest::sha384(), Es384),
+            ("es512", MessageDigest::sha512(), Es512),
+        ];
+
+        let mut store = BTreeMap::new();
+        for (name, digest, algorithm) in fixtures.iter() {
+            let public_pem = std::fs::read(format!("test/{}-public.pem", name)).unwrap();
+            let private_pem = std::fs::read(format!("test/{}-private.pem", name)).unwrap();
+            let public_key = PKeyWithDigest::new(PKey::public_key_from_pem(&public_pem)?, *digest)?;
+            let private_key =
+                PKeyWithDigest::new(PKey::private_key_from_pem(&private_pem)?, *digest)?;
+
+            let exported = public_key.to_jwk()?;
+            assert_eq!(exported.parameters, jwk(name, "public").parameters);
+            assert_eq!(exported.algorithm.as_ref(), Some(algorithm));
+            assert_eq!(exported.key_use, Some(KeyUse::Signature));
+            assert!(exported.key_id.is_none());
+            assert_eq!(private_key.to_jwk()?, exported);
+
+            store.insert(name.to_string(), public_key);
+        }
+
+        for name in ["ed25519", "ed448"].iter() {
+            let pem = std::fs::read(format!("test/{}-public.pem", name)).unwrap();
+            let key = EdDsaPKey::new(PKey::public_key_from_pem(&pem)?)?;
+            assert_eq!(key.to_jwk()?.parameters, jwk(name, "public").parameters);
+        }
+
+        let pem = include_bytes!("../../test/rs2048-public.pem");
+        let key = PssPKeyWithDigest::new(PKey::public_key_from_pem(pem)?, MessageDigest::sha384())?;
+        assert_eq!(key.to_jwk()?.algorithm, Some(Ps384));
+
+        let set = JwkSet::from_public_keys(&store)?;
+        let json = serde_json::to_string(&set)?;
+        let published: JwkSet = serde_json::from_str(&json)?;
+        for (name, _, algorithm) in fixtures.iter() {
+            let (exported, _) = published.get_all(name).next().unwrap();
+            assert_eq!(exported.algorithm.as_ref(), Some(algorithm));
+            assert_eq!(exported.key_use, Some(KeyUse::Signature));
+            assert!(!exported.is_private());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pem_loaders() -> Result<(), Error> {
+        use crate::algorithm::openssl::{signing_key_from_pem, verifying_key_from_pem};
+        // The glob import of `AlgorithmType` shadows `Option::None`.
+        use std::option::Option::None;
+
+        let fixtures = [
+            ("rs2048", Rs256),
+            ("es256", Es256),
+            ("es256k", Es256k),
+            ("es384", Es384),
+            ("es512", Es512),
+            ("ed25519", EdDsa),
+            ("ed448", EdDsa),
+        ];
+        let header = AlgOnly(Rs256).to_base64()?;
+        for (name, algorithm) in fixtures.iter() {
+            let private_pem = std::fs::read(format!("test/{}-private.pem", name)).unwrap();
+            let public_pem = std::fs::read(format!("test/{}-public.pem", name)).unwrap();
+            let signing_key = signing_key_from_pem(&private_pem, None)?;
+            let verifying_key = verifying_key_from_pem(&public_pem, None)?;
+            let derived_key = verifying_key_from_pem(&private_pem, Some(algorithm.clone()))?;
+            assert_eq!(SigningAlgorithm::algorithm_type(&*signing_key), *algorithm);
+            assert_eq!(
+                VerifyingAlgorithm::algorithm_type(&*verifying_key),
+                *algorithm
+            );
+
+            let signature = signing_key.sign(&header, CLAIMS)?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+            assert!(derived_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        for name in ["es256", "es384"].iter() {
+            let pem = std::fs::read(format!("test/{}-private-pkcs8.pem", name)).unwrap();
+            signing_key_from_pem(&pem, None)?;
+        }
+
+        let private_pem = include_bytes!("../../test/rs2048-private.pem");
+        let public_pem = include_bytes!("../../test/rs2048-public.pem");
+        for algorithm in [Rs384, Rs512, Ps256, Ps384, Ps512].iter() {
+            let signing_key = signing_key_from_pem(private_pem, Some(algorithm.clone()))?;
+            let verifying_key = verifying_key_from_pem(public_pem, Some(algorithm.clone()))?;
+            assert_eq!(SigningAlgorithm::algorithm_type(&*signing_key), *algorithm);
+            let signature = signing_key.sign(&header, CLAIMS)?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        let pkcs1_pem = Rsa::public_key_from_pem(public_pem)?.public_key_to_pem_pkcs1()?;
+        let key = verifying_key_from_pem(&pkcs1_pem, None)?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&*key), Rs256);
+        Ok(())
+    }
+
+    #[test]
+    fn der_loaders() -> Result<(), Error> {
+        use crate::algorithm::openssl::{signing_key_from_der, verifying_key_from_der};
+        // The glob import of `AlgorithmType` shadows `Option::None`.
+        use std::option::Option::None;
+
+        let header = AlgOnly(Es256).to_base64()?;
+        let private_key =
+            PKey::private_key_from_pem(include_bytes!("../../test/es256-private.pem"))?;
+        let signing_key = signing_key_from_der(&private_key.private_key_to_der()?, None)?;
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        for der in [
+            private_key.public_key_to_der()?,
+            private_key.private_key_to_der()?,
+            private_key.private_key_to_pkcs8()?,
+        ]
+        .iter()
+        {
+            let verifying_key = verifying_key_from_der(der, None)?;
+            assert_eq!(VerifyingAlgorithm::algorithm_type(&*verifying_key), Es256);
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        let rsa = Rsa::private_key_from_pem(include_bytes!("../../test/rs2048-private.pem"))?;
+        signing_key_from_der(&rsa.private_key_to_der()?, Some(Ps256))?;
+        let key = verifying_key_from_der(&rsa.public_key_to_der_pkcs1()?, None)?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&*key), Rs256);
+        Ok(())
+    }
+
+    #[test]
+    fn certificate_loader() -> Result<(), Error> {
+        use crate::algorithm::openssl::{verifying_key_from_der, verifying_key_from_pem};
+        // The glob import of `AlgorithmType` shadows `Option::None`.
+        use openssl::asn1::Asn1Time;
+        use openssl::x509::X509;
+        use std::option::Option::None;
+
+        let private_key =
+            PKey::private_key_from_pem(include_bytes!("../../test/es384-private.pem"))?;
+        let mut builder = X509::builder()?;
+        builder.set_pubkey(&private_key)?;
+        builder.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+        builder.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+        builder.sign(&private_key, MessageDigest::sha384())?;
+        let certificate = builder.build();
+
+        let key = verifying_key_from_pem(&certificate.to_pem()?, None)?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&*key), Es384);
+        let key = verifying_key_from_der(&certificate.to_der()?, None)?;
+        assert_eq!(VerifyingAlgorithm::algorithm_type(&*key), Es384);
+        Ok(())
+    }
+
+    #[test]
+    fn loaders_reject_unsupported_input() {
+        use crate::algorithm::openssl::{
+            signing_key_from_der, signing_key_from_pem, verifying_key_from_pem,
+        };
+        // The glob import of `AlgorithmType` shadows `Option::None`.
+        use std::option::Option::None;
+
+        let es256_pem = include_bytes!("../../test/es256-private.pem");
+        let rs2048_pem = include_bytes!("../../test/rs2048-private.pem");
+        let cases = [
+            signing_key_from_pem(es256_pem, Some(Es384)).map(|_| ()),
+            signing_key_from_pem(es256_pem, Some(Rs256)).map(|_| ()),
+            signing_key_from_pem(rs2048_pem, Some(EdDsa)).map(|_| ()),
+            signing_key_from_pem(include_bytes!("../../test/rs256-private.pem"), None).map(|_| ()),
+            signing_key_from_pem(include_bytes!("../../test/rs2048-public.pem"), None).map(|_| ()),
+            signing_key_from_pem(b"not a key", None).map(|_| ()),
+            signing_key_from_der(b"not a key", None).map(|_| ()),
+            verifying_key_from_pem(b"-----BEGIN DH PARAMETERS-----", None).map(|_| ()),
+        ];
+        for result in cases.iter() {
+            match result {
+                Err(Error::InvalidKey(_)) => (),
+                other => panic!("Wrong result: {:?}", other),
+            }
+        }
+
+        match signing_key_from_pem(rs2048_pem, Some(Hs256)) {
+            Err(Error::UnsupportedAlgorithm(Hs256)) => (),
+            other => panic!("Wrong result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn encrypted_loaders() -> Result<(), Error> {
+        use crate::algorithm::openssl::{
+            signing_key_from_encrypted_der, signing_key_from_encrypted_pem, signing_key_from_pem,
+            signing_key_from_pkcs12, verifying_key_from_pem,
+        };
+        use openssl::pkcs12::Pkcs12;
+        use openssl::symm::Cipher;
+        // The glob import of `AlgorithmType` shadows `Option::None`.
+        use std::option::Option::None;
+
+        let passphrase = || Ok("secret".to_owned());
+        let header = AlgOnly(Es256).to_base64()?;
+        let private_key =
+            PKey::private_key_from_pem(include_bytes!("../../test/es256-private.pem"))?;
+        let verifying_key =
+            verifying_key_from_pem(include_bytes!("../../test/es256-public.pem"), None)?;
+
+        let pkcs8_pem = include_bytes!("../../test/es256-private-encrypted.pem");
+        let pkcs8_der =
+            private_key.private_key_to_pkcs8_passphrase(Cipher::aes_256_cbc(), b"secret")?;
+        let legacy_pem = private_key
+            .ec_key()?
+            .private_key_to_pem_passphrase(Cipher::aes_128_cbc(), b"secret")?;
+        let mut builder = Pkcs12::builder();
+        builder.name("es256").pkey(&private_key);
+        let pkcs12 = builder.build2("secret")?.to_der()?;
+
+        let keys = [
+            signing_key_from_encrypted_pem(pkcs8_pem, passphrase, None)?,
+            signing_key_from_encrypted_pem(&legacy_pem, passphrase, None)?,
+            signing_key_from_encrypted_der(&pkcs8_der, passphrase, None)?,
+            signing_key_from_pkcs12(&pkcs12, passphrase, Some(Es256))?,
+        ];
+        for key in keys.iter() {
+            let signature = key.sign(&header, CLAIMS)?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        let wrong = || Ok("wrong".to_owned());
+        let results = [
+            signing_key_from_encrypted_pem(pkcs8_pem, wrong, None).map(|_| ()),
+            signing_key_from_encrypted_pem(&legacy_pem, wrong, None).map(|_| ()),
+            signing_key_from_encrypted_der(&pkcs8_der, wrong, None).map(|_| ()),
+            signing_key_from_pkcs12(&pkcs12, wrong, None).map(|_| ()),
+        ];
+        for result in results.iter() {
+            match result {
+                Err(Error::InvalidPassphrase) => (),
+                other => panic!("Wrong result: {:?}", other),
+            }
+        }
+
+        let rc2_pem = include_bytes!("../../test/es256-private-rc2.pem");
+        match signing_key_from_encrypted_pem(rc2_pem, passphrase, None) {
+            Err(Error::UnsupportedKeyEncryption(_)) => (),
+            other => panic!("Wrong result: {:?}", other.map(|_| ())),
+        }
+
+        let mut corrupt_pem = pkcs8_pem.to_vec();
+        corrupt_pem[100..140].copy_from_slice(&[b'A'; 40]);
+        let truncated_der = &pkcs8_der[..pkcs8_der.len() - 20];
+        let results = [
+            signing_key_from_encrypted_pem(&corrupt_pem, passphrase, None).map(|_| ()),
+            signing_key_from_encrypted_der(truncated_der, passphrase, None).map(|_| ()),
+        ];
+        for result in results.iter() {
+            match result {
+                Err(Error::InvalidKey(_)) => (),
+                other => panic!("Wrong result: {:?}", other),
+            }
+        }
+
+        let plain_pem = include_bytes!("../../test/es256-private.pem");
+        let results = [
+            signing_key_from_pem(pkcs8_pem, None).map(|_| ()),
+            signing_key_from_pem(&legacy_pem, None).map(|_| ()),
+            verifying_key_from_pem(pkcs8_pem, None).map(|_| ()),
+            signing_key_from_encrypted_pem(plain_pem, passphrase, None).map(|_| ()),
+            signing_key_from_encrypted_der(&private_key.private_key_to_der()?, passphrase, None)
+                .map(|_| ()),
+            signing_key_from_pkcs12(&pkcs8_der, passphrase, None).map(|_| ()),
+        ];
+        for result in results.iter() {
+            match result {
+                Err(Error::InvalidKey(_)) => (),
+                other => panic!("Wrong result: {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generated_keys() -> Result<(), Error> {
+        use crate::algorithm::generate::GeneratedKey;
+        use crate::algorithm::openssl::{signing_key_from_pem, verifying_key_from_pem};
+        use crate::jwk::ToJwk;
+        use hmac::Hmac;
+        use sha2::Sha384;
+
+        let algorithms = [
+            Hs256, Hs384, Hs512, Rs256, Rs384, Rs512, Ps256, Ps384, Ps512, Es256, Es256k, Es384,
+            Es512, EdDsa,
+        ];
+        for algorithm in algorithms.iter() {
+            let key = GeneratedKey::generate(algorithm.clone())?;
+            assert_eq!(key.algorithm_type(), *algorithm);
+            assert_eq!(
+                SigningAlgorithm::algorithm_type(&*key.signing_key),
+                *algorithm
+            );
+            let header = AlgOnly(algorithm.clone()).to_base64()?.into_owned();
+            let signature = key.signing_key.sign(&header, CLAIMS)?;
+            assert!(key.verifying_key.verify(&header, CLAIMS, &signature)?);
+
+            let private_jwk = key.to_private_jwk()?;
+            assert!(private_jwk.is_private());
+            assert_eq!(private_jwk.key_id.as_deref(), Some(key.key_id.as_str()));
+            assert_eq!(private_jwk.algorithm.as_ref(), Some(algorithm));
+            if let KeyParameters::OctetSequence(_) = private_jwk.parameters {
+                assert!(key.to_jwk().is_err());
+                assert!(key.private_key_to_pem().is_err());
+                continue;
+            }
+
+            let public_jwk = key.to_jwk()?;
+            assert!(!public_jwk.is_private());
+            assert_eq!(public_jwk.thumbprint(), key.key_id);
+            assert_eq!(private_jwk.thumbprint(), key.key_id);
+
+            let signing_key =
+                signing_key_from_pem(&key.private_key_to_pem()?, Some(algorithm.clone()))?;
+            let verifying_key =
+                verifying_key_from_pem(&key.public_key_to_pem()?, Some(algorithm.clone()))?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signing_key.sign(&header, CLAIMS)?)?);
+        }
+
+        let key = GeneratedKey::generate(Hs384)?;
+        let secret = Hmac::<Sha384>::try_from(&key.to_private_jwk()?)?;
+        let header = AlgOnly(Hs384).to_base64()?;
+        let signature = key.signing_key.sign(&header, CLAIMS)?;
+        assert!(VerifyingAlgorithm::verify(
+            &secret, &header, CLAIMS, &signature
+        )?);
+        assert_ne!(key.key_id, GeneratedKey::generate(Hs384)?.key_id);
+
+        let key = GeneratedKey::generate(Es256)?;
+        let private_key = PKeyWithDigest::<Private>::try_from(&key.to_private_jwk()?)?;
+        let header = AlgOnly(Es256).to_base64()?;
+        let signature = private_key.sign(&header, CLAIMS)?;
+        assert!(key.verifying_key.verify(&header, CLAIMS, &signature)?);
+
+        match GeneratedKey::generate(None) {
+            Err(Error::UnsupportedAlgorithm(None)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other.map(|_| ())),
+        }
     }
 }
@@ -0,0 +1,950 @@
+//! ring support through the ring crate, enabled with the `ring` feature.
+//! Ed25519 key pairs can be used directly. ring HMAC keys, RSA and ECDSA key
+//! pairs and public keys are not tied to a single JWA algorithm, so they are
+//! wrapped together with the algorithm type they are used for.
+//! ## Examples
+//! ```
+//! use jwt::algorithm::ring::{HmacKey, PublicKeyWithAlgorithm};
+//! use jwt::AlgorithmType;
+//!
+//! let hs256_key = HmacKey::new(AlgorithmType::Hs256, b"some-secret").unwrap();
+//!
+//! # let public_key_bytes = vec![];
+//! let es256_public_key =
+//!     PublicKeyWithAlgorithm::new(AlgorithmType::Es256, public_key_bytes).unwrap();
+//! ```
+
+use std::convert::TryFrom;
+
+use ring::hmac;
+use ring::rand::SystemRandom;
+use ring::rsa::{KeyPairComponents, PublicKeyComponents};
+use ring::signature::{
+    self, EcdsaKeyPair, EcdsaSigningAlgorithm, Ed25519KeyPair, KeyPair, RsaEncoding, RsaKeyPair,
+    UnparsedPublicKey, VerificationAlgorithm,
+};
+
+use crate::algorithm::generate::GeneratedKey;
+use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+use crate::error::Error;
+use crate::jwk::{
+    private_parameter, EllipticCurve, EllipticCurveParameters, Jwk, KeyParameters,
+    OctetKeyPairCurve, OctetKeyPairParameters, RsaParameters, ToJwk,
+};
+use crate::SEPARATOR;
+
+/// A ring [hmac::Key](../../../ring/hmac/struct.Key.html) for `HS256`,
+/// `HS384` or `HS512`.
+pub struct HmacKey {
+    algorithm_type: AlgorithmType,
+    key: hmac::Key,
+}
+
+impl HmacKey {
+    pub fn new(algorithm_type: AlgorithmType, secret: &[u8]) -> Result<Self, Error> {
+        let algorithm = match algorithm_type {
+            AlgorithmType::Hs256 => hmac::HMAC_SHA256,
+            AlgorithmType::Hs384 => hmac::HMAC_SHA384,
+            AlgorithmType::Hs512 => hmac::HMAC_SHA512,
+            _ => return Err(Error::UnsupportedAlgorithm(algorithm_type)),
+        };
+
+        Ok(HmacKey {
+            algorithm_type,
+            key: hmac::Key::new(algorithm, secret),
+        })
+    }
+}
+
+impl SigningAlgorithm for HmacKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm_type.clone()
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let mut context = hmac::Context::with_key(&self.key);
+        context.update(header.as_bytes());
+        context.update(SEPARATOR.as_bytes());
+        context.update(claims.as_bytes());
+        let tag = context.sign();
+        Ok(tag.as_ref().to_vec())
+    }
+}
+
+impl VerifyingAlgorithm for HmacKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm_type.clone()
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        let message = signing_input(header, claims);
+        Ok(hmac::verify(&self.key, message.as_bytes(), signature).is_ok())
+    }
+}
+
+impl TryFrom<&Jwk> for HmacKey {
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        let parameters = jwk.octet_sequence_parameters()?;
+        HmacKey::new(jwk.algorithm_type()?, &parameters.key_value)
+    }
+}
+
+impl SigningAlgorithm for Ed25519KeyPair {
+    fn algorithm_type(&self) -> AlgorithmType {
+        AlgorithmType::EdDsa
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let message = signing_input(header, claims);
+        let signature = Ed25519KeyPair::sign(self, message.as_bytes());
+        Ok(signature.as_ref().to_vec())
+    }
+}
+
+impl TryFrom<&Jwk> for Ed25519KeyPair {
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        jwk.check_algorithm(&AlgorithmType::EdDsa)?;
+        let parameters = ed25519_parameters(jwk)?;
+        let private_key = private_parameter(&parameters.private_key, "d")?;
+        Ok(Ed25519KeyPair::from_seed_and_public_key(
+            private_key,
+            &parameters.public_key,
+        )?)
+    }
+}
+
+impl ToJwk for Ed25519KeyPair {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        public_key_to_jwk(&AlgorithmType::EdDsa, self.public_key().as_ref())
+    }
+}
+
+/// A ring [RsaKeyPair](../../../ring/rsa/struct.KeyPair.html) with the
+/// algorithm type it signs for, one of `RS256`, `RS384`, `RS512`, `PS256`,
+/// `PS384` or `PS512`.
+pub struct RsaKeyPairWithAlgorithm {
+    algorithm_type: AlgorithmType,
+    padding: &'static dyn RsaEncoding,
+    key_pair: RsaKeyPair,
+}
+
+impl RsaKeyPairWithAlgorithm {
+    pub fn new(algorithm_type: AlgorithmType, key_pair: RsaKeyPair) -> Result<Self, Error> {
+        let padding: &'static dyn RsaEncoding = match algorithm_type {
+            AlgorithmType::Rs256 => &signature::RSA_PKCS1_SHA256,
+            AlgorithmType::Rs384 => &signature::RSA_PKCS1_SHA384,
+            AlgorithmType::Rs512 => &signature::RSA_PKCS1_SHA512,
+            AlgorithmType::Ps256 => &signature::RSA_PSS_SHA256,
+            AlgorithmType::Ps384 => &signature::RSA_PSS_SHA384,
+            AlgorithmType::Ps512 => &signature::RSA_PSS_SHA512,
+            _ => return Err(Error::UnsupportedAlgorithm(algorithm_type)),
+        };
+
+        Ok(RsaKeyPairWithAlgorithm {
+            algorithm_type,
+            padding,
+            key_pair,
+        })
+    }
+
+    pub fn key_pair(&self) -> &RsaKeyPair {
+        &self.key_pair
+    }
+}
+
+/// Converts a private RSA JWK, which has to name its algorithm.
+impl TryFrom<&Jwk> for RsaKeyPairWithAlgorithm {
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        let algorithm_type = jwk.algorithm_type()?;
+        let parameters = jwk.rsa_parameters()?;
+        let components = KeyPairComponents {
+            public_key: PublicKeyComponents {
+                n: parameters.modulus.as_slice(),
+                e: parameters.exponent.as_slice(),
+            },
+            d: private_parameter(&parameters.private_exponent, "d")?,
+            p: private_parameter(&parameters.first_prime, "p")?,
+            q: private_parameter(&parameters.second_prime, "q")?,
+            dP: private_parameter(&parameters.first_prime_exponent, "dp")?,
+            dQ: private_parameter(&parameters.second_prime_exponent, "dq")?,
+            qInv: private_parameter(&parameters.coefficient, "qi")?,
+        };
+        let key_pair = RsaKeyPair::from_components(&components)?;
+        RsaKeyPairWithAlgorithm::new(algorithm_type, key_pair)
+    }
+}
+
+impl ToJwk for RsaKeyPairWithAlgorithm {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        let components = PublicKeyComponents::<Vec<u8>>::from(self.key_pair.public());
+        let parameters = RsaParameters::new(components.n, components.e);
+        Ok(Jwk::signature_key(
+            KeyParameters::Rsa(parameters),
+            self.algorithm_type.clone(),
+        ))
+    }
+}
+
+impl SigningAlgorithm for RsaKeyPairWithAlgorithm {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm_type.clone()
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let message = signing_input(header, claims);
+        let mut signature = vec![0; self.key_pair.public().modulus_len()];
+        self.key_pair.sign(
+            self.padding,
+            &SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )?;
+        Ok(signature)
+    }
+}
+
+/// A ring [EcdsaKeyPair](../../../ring/signature/struct.EcdsaKeyPair.html)
+/// with the algorithm type it signs for, either `ES256` or `ES384`.
+pub struct EcdsaKeyPairWithAlgorithm {
+    algorithm_type: AlgorithmType,
+    key_pair: EcdsaKeyPair,
+}
+
+impl EcdsaKeyPairWithAlgorithm {
+    /// Parse a PKCS#8 encoded private key for the curve of `algorithm_type`.
+    pub fn from_pkcs8(algorithm_type: AlgorithmType, pkcs8: &[u8]) -> Result<Self, Error> {
+        let signing_algorithm = ecdsa_signing_algorithm(&algorithm_type)?;
+        let key_pair = EcdsaKeyPair::from_pkcs8(signing_algorithm, pkcs8, &SystemRandom::new())?;
+
+        Ok(EcdsaKeyPairWithAlgorithm {
+            algorithm_type,
+            key_pair,
+        })
+    }
+
+    pub fn key_pair(&self) -> &EcdsaKeyPair {
+        &self.key_pair
+    }
+}
+
+impl TryFrom<&Jwk> for EcdsaKeyPairWithAlgorithm {
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        let algorithm_type = jwk.algorithm_type()?;
+        let signing_algorithm = ecdsa_signing_algorithm(&algorithm_type)?;
+        let parameters = jwk.elliptic_curve_parameters()?;
+        let key_pair = EcdsaKeyPair::from_private_key_and_public_key(
+            signing_algorithm,
+            private_parameter(&parameters.private_key, "d")?,
+            &parameters.uncompressed_point(),
+            &SystemRandom::new(),
+        )?;
+
+        Ok(EcdsaKeyPairWithAlgorithm {
+            algorithm_type,
+            key_pair,
+        })
+    }
+}
+
+impl ToJwk for EcdsaKeyPairWithAlgorithm {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        public_key_to_jwk(&self.algorithm_type, self.key_pair.public_key().as_ref())
+    }
+}
+
+fn ecdsa_signing_algorithm(
+    algorithm_type: &AlgorithmType,
+) -> Result<&'static EcdsaSigningAlgorithm, Error> {
+    match *algorithm_type {
+        AlgorithmType::Es256 => Ok(&signature::ECDSA_P256_SHA256_FIXED_SIGNING),
+        AlgorithmType::Es384 => Ok(&signature::ECDSA_P384_SHA384_FIXED_SIGNING),
+        _ => Err(Error::UnsupportedAlgorithm(algorithm_type.clone())),
+    }
+}
+
+impl SigningAlgorithm for EcdsaKeyPairWithAlgorithm {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm_type.clone()
+    }
+
+    fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+        let message = signing_input(header, claims);
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), message.as_bytes())?;
+        Ok(signature.as_ref().to_vec())
+    }
+}
+
+/// A public key with the algorithm type it verifies. RSA keys are kept as
+/// their modulus and public exponent, ECDSA keys as uncompressed points and
+/// Ed25519 keys as the raw 32 bytes.
+pub struct PublicKeyWithAlgorithm {
+    algorithm_type: AlgorithmType,
+    key: PublicKey,
+}
+
+enum PublicKey {
+    Rsa(
+        &'static signature::RsaParameters,
+        PublicKeyComponents<Vec<u8>>,
+    ),
+    Unparsed(UnparsedPublicKey<Vec<u8>>),
+}
+
+impl PublicKeyWithAlgorithm {
+    /// Creates an ECDSA or Ed25519 public key from its bytes. RSA public keys
+    /// are created from their components with
+    /// [from_rsa_components](#method.from_rsa_components).
+    pub fn new(algorithm_type: AlgorithmType, bytes: Vec<u8>) -> Result<Self, Error> {
+        let verification_algorithm: &'static dyn VerificationAlgorithm = match algorithm_type {
+            AlgorithmType::Es256 => &signature::ECDSA_P256_SHA256_FIXED,
+            AlgorithmType::Es384 => &signature::ECDSA_P384_SHA384_FIXED,
+            AlgorithmType::EdDsa => &signature::ED25519,
+            AlgorithmType::Rs256
+            | AlgorithmType::Rs384
+            | AlgorithmType::Rs512
+            | AlgorithmType::Ps256
+            | AlgorithmType::Ps384
+            | AlgorithmType::Ps512 => {
+                return Err(Error::InvalidKey(
+                    "RSA public keys have to be created from their components".to_owned(),
+                ))
+            }
+            _ => return Err(Error::UnsupportedAlgorithm(algorithm_type)),
+        };
+
+        Ok(PublicKeyWithAlgorithm {
+            algorithm_type,
+            key: PublicKey::Unparsed(UnparsedPublicKey::new(verification_algorithm, bytes)),
+        })
+    }
+
+    /// Creates an RSA public key from its big-endian modulus and public
+    /// exponent.
+    pub fn from_rsa_components(
+        algorithm_type: AlgorithmType,
+        components: PublicKeyComponents<Vec<u8>>,
+    ) -> Result<Self, Error> {
+        let parameters: &'static signature::RsaParameters = match algorithm_type {
+            AlgorithmType::Rs256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+            AlgorithmType::Rs384 => &signature::RSA_PKCS1_2048_8192_SHA384,
+            AlgorithmType::Rs512 => &signature::RSA_PKCS1_2048_8192_SHA512,
+            AlgorithmType::Ps256 => &signature::RSA_PSS_2048_8192_SHA256,
+            AlgorithmType::Ps384 => &signature::RSA_PSS_2048_8192_SHA384,
+            AlgorithmType::Ps512 => &signature::RSA_PSS_2048_8192_SHA512,
+            _ => return Err(Error::UnsupportedAlgorithm(algorithm_type)),
+        };
+
+        Ok(PublicKeyWithAlgorithm {
+            algorithm_type,
+            key: PublicKey::Rsa(parameters, components),
+        })
+    }
+}
+
+/// Converts a public or private JWK into a public key in the format ring
+/// expects for the algorithm of the JWK.
+impl TryFrom<&Jwk> for PublicKeyWithAlgorithm {
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        let algorithm_type = jwk.algorithm_type()?;
+        let bytes = match jwk.parameters {
+            KeyParameters::Rsa(ref parameters) => {
+                let components = PublicKeyComponents {
+                    n: parameters.modulus.clone(),
+                    e: parameters.exponent.clone(),
+                };
+                return PublicKeyWithAlgorithm::from_rsa_components(algorithm_type, components);
+            }
+            KeyParameters::EllipticCurve(_) => {
+                jwk.elliptic_curve_parameters()?.uncompressed_point()
+            }
+            KeyParameters::OctetKeyPair(_) => ed25519_parameters(jwk)?.public_key.clone(),
+            KeyParameters::OctetSequence(_) => {
+                return Err(Error::InvalidKey(
+                    "symmetric keys can not be used as public keys".to_owned(),
+                ))
+            }
+        };
+        PublicKeyWithAlgorithm::new(algorithm_type, bytes)
+    }
+}
+
+impl ToJwk for PublicKeyWithAlgorithm {
+    fn to_jwk(&self) -> Result<Jwk, Error> {
+        match self.key {
+            PublicKey::Rsa(_, ref components) => {
+                let parameters = RsaParameters::new(components.n.clone(), components.e.clone());
+                Ok(Jwk::signature_key(
+                    KeyParameters::Rsa(parameters),
+                    self.algorithm_type.clone(),
+                ))
+            }
+            PublicKey::Unparsed(ref key) => public_key_to_jwk(&self.algorithm_type, key.as_ref()),
+        }
+    }
+}
+
+impl VerifyingAlgorithm for PublicKeyWithAlgorithm {
+    fn algorithm_type(&self) -> AlgorithmType {
+        self.algorithm_type.clone()
+    }
+
+    fn verify_bytes(&self, header: &str, claims: &str, signature: &[u8]) -> Result<bool, Error> {
+        let message = signing_input(header, claims);
+        let result = match self.key {
+            PublicKey::Rsa(parameters, ref components) => {
+                components.verify(parameters, message.as_bytes(), signature)
+            }
+            PublicKey::Unparsed(ref key) => key.verify(message.as_bytes(), signature),
+        };
+        Ok(result.is_ok())
+    }
+}
+
+/// Convert an ECDSA or Ed25519 public key in the format ring uses for
+/// `algorithm_type` to a JWK.
+fn public_key_to_jwk(algorithm_type: &AlgorithmType, bytes: &[u8]) -> Result<Jwk, Error> {
+    let parameters = match *algorithm_type {
+        AlgorithmType::Es256 | AlgorithmType::Es384 => {
+            let curve = if *algorithm_type == AlgorithmType::Es256 {
+                EllipticCurve::P256
+            } else {
+                EllipticCurve::P384
+            };
+            let size = curve.coordinate_size();
+            if bytes.len() != 1 + 2 * size || bytes[0] != 0x04 {
+                return Err(Error::InvalidKey(
+                    "EC public key is not an uncompressed point".to_owned(),
+                ));
+            }
+            KeyParameters::EllipticCurve(EllipticCurveParameters {
+                curve,
+                x_coordinate: bytes[1..1 + size].to_vec(),
+                y_coordinate: bytes[1 + size..].to_vec(),
+                private_key: None,
+            })
+        }
+        AlgorithmType::EdDsa => KeyParameters::OctetKeyPair(OctetKeyPairParameters {
+            curve: OctetKeyPairCurve::Ed25519,
+            public_key: bytes.to_vec(),
+            private_key: None,
+        }),
+        ref other => return Err(Error::UnsupportedAlgorithm(other.clone())),
+    };
+    Ok(Jwk::signature_key(parameters, algorithm_type.clone()))
+}
+
+/// ring only implements EdDSA for Ed25519 keys.
+fn ed25519_parameters(jwk: &Jwk) -> Result<&OctetKeyPairParameters, Error> {
+    match jwk.parameters {
+        KeyParameters::OctetKeyPair(ref parameters)
+            if parameters.curve == OctetKeyPairCurve::Ed25519 =>
+        {
+            Ok(parameters)
+        }
+        _ => Err(Error::InvalidKey(
+            "only Ed25519 keys are supported for EdDSA".to_owned(),
+        )),
+    }
+}
+
+/// How ring lays out the PKCS #8 documents it generates for a key type.
+/// ring fills fixed templates, so the private key that the private JWK needs
+/// follows `pkcs8_prefix` directly. `spki_prefix` is the start of the
+/// SubjectPublicKeyInfo that the public key bytes complete.
+struct GeneratedLayout {
+    pkcs8_prefix: &'static [u8],
+    private_key_length: usize,
+    spki_prefix: &'static [u8],
+}
+
+const P256_LAYOUT: GeneratedLayout = GeneratedLayout {
+    pkcs8_prefix: b"\x30\x81\x87\x02\x01\x00\x30\x13\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x08\x2a\x86\x48\xce\x3d\x03\x01\x07\x04\x6d\x30\x6b\x02\x01\x01\x04\x20",
+    private_key_length: 32,
+    spki_prefix: b"\x30\x59\x30\x13\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x08\x2a\x86\x48\xce\x3d\x03\x01\x07\x03\x42\x00",
+};
+
+const P384_LAYOUT: GeneratedLayout = GeneratedLayout {
+    pkcs8_prefix: b"\x30\x81\xb6\x02\x01\x00\x30\x10\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x05\x2b\x81\x04\x00\x22\x04\x81\x9e\x30\x81\x9b\x02\x01\x01\x04\x30",
+    private_key_length: 48,
+    spki_prefix: b"\x30\x76\x30\x10\x06\x07\x2a\x86\x48\xce\x3d\x02\x01\x06\x05\x2b\x81\x04\x00\x22\x03\x62\x00",
+};
+
+const ED25519_LAYOUT: GeneratedLayout = GeneratedLayout {
+    pkcs8_prefix: b"\x30\x51\x02\x01\x01\x30\x05\x06\x03\x2b\x65\x70\x04\x22\x04\x20",
+    private_key_length: 32,
+    spki_prefix: b"\x30\x2a\x30\x05\x06\x03\x2b\x65\x70\x03\x21\x00",
+};
+
+impl GeneratedLayout {
+    fn private_key<'a>(&self, pkcs8: &'a [u8]) -> Result<&'a [u8], Error> {
+        let end = self.pkcs8_prefix.len() + self.private_key_length;
+        if !pkcs8.starts_with(self.pkcs8_prefix) || pkcs8.len() < end {
+            return Err(Error::InvalidKey(
+                "unexpected PKCS #8 document generated by ring".to_owned(),
+            ));
+        }
+        Ok(&pkcs8[self.pkcs8_prefix.len()..end])
+    }
+
+    fn public_key_pem(&self, public_key: &[u8]) -> Vec<u8> {
+        let mut spki = self.spki_prefix.to_vec();
+        spki.extend_from_slice(public_key);
+        pem("PUBLIC KEY", &spki)
+    }
+}
+
+/// Generate an ES256, ES384 or EdDSA key with ring. Other algorithms return
+/// `Error::UnsupportedAlgorithm`.
+pub(crate) fn generate_key(algorithm: AlgorithmType) -> Result<GeneratedKey, Error> {
+    let rng = SystemRandom::new();
+    let (layout, pkcs8, signing_key, public_key): (
+        _,
+        _,
+        Box<dyn SigningAlgorithm + Send + Sync>,
+        _,
+    ) = match algorithm {
+        AlgorithmType::Es256 | AlgorithmType::Es384 => {
+            let layout = if algorithm == AlgorithmType::Es256 {
+                &P256_LAYOUT
+            } else {
+                &P384_LAYOUT
+            };
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(ecdsa_signing_algorithm(&algorithm)?, &rng)?;
+            let key = EcdsaKeyPairWithAlgorithm::from_pkcs8(algorithm.clone(), pkcs8.as_ref())?;
+            let public_key = key.key_pair().public_key().as_ref().to_vec();
+            (layout, pkcs8, Box::new(key), public_key)
+        }
+        AlgorithmType::EdDsa => {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)?;
+            let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())?;
+            let public_key = key.public_key().as_ref().to_vec();
+            (&ED25519_LAYOUT, pkcs8, Box::new(key), public_key)
+        }
+        other => return Err(Error::UnsupportedAlgorithm(other)),
+    };
+
+    let private_key = layout.private_key(pkcs8.as_ref())?.to_vec();
+    let public_key_pem = layout.public_key_pem(&public_key);
+    let verifying_key = PublicKeyWithAlgorithm::new(algorithm.clone(), public_key)?;
+    let public_jwk = verifying_key.to_jwk()?;
+    let parameters = match public_jwk.parameters.clone() {
+        KeyParameters::EllipticCurve(parameters) => {
+            KeyParameters::EllipticCurve(EllipticCurveParameters {
+                private_key: Some(private_key),
+                ..parameters
+            })
+        }
+        KeyParameters::OctetKeyPair(parameters) => {
+            KeyParameters::OctetKeyPair(OctetKeyPairParameters {
+                private_key: Some(private_key),
+                ..parameters
+            })
+        }
+        _ => unreachable!("ring only generates EC and OKP keys"),
+    };
+    let private_jwk = Jwk {
+        parameters,
+        ..public_jwk.clone()
+    };
+
+    Ok(GeneratedKey::from_key_pair(
+        signing_key,
+        Box::new(verifying_key),
+        private_jwk,
+        public_jwk,
+        pem("PRIVATE KEY", pkcs8.as_ref()),
+        public_key_pem,
+    ))
+}
+
+/// PEM encode a DER document with the given label.
+fn pem(label: &str, der: &[u8]) -> Vec<u8> {
+    let base64 = base64::encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in base64.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem.into_bytes()
+}
+
+/// ring can not sign or verify incrementally, so the whole input is needed at
+/// once.
+fn signing_input(header: &str, claims: &str) -> String {
+    [header, claims].join(SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::rsa::PublicKeyComponents;
+    use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+
+    use crate::algorithm::ring::{
+        EcdsaKeyPairWithAlgorithm, HmacKey, PublicKeyWithAlgorithm, RsaKeyPairWithAlgorithm,
+    };
+    use crate::algorithm::AlgorithmType::*;
+    use crate::algorithm::{SigningAlgorithm, VerifyingAlgorithm};
+    use crate::error::Error;
+    use crate::header::PrecomputedAlgorithmOnlyHeader as AlgOnly;
+    use crate::jwk::{Jwk, KeyParameters};
+    use crate::ToBase64;
+
+    use std::convert::TryFrom;
+
+    // {"sub":"1234567890","name":"John Doe","admin":true}
+    const CLAIMS: &str = "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+
+    fn pem_body(pem: &str) -> Vec<u8> {
+        let body: String = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        base64::decode(body).unwrap()
+    }
+
+    #[test]
+    fn hs256() -> Result<(), Error> {
+        let header = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+        let claims = "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+        let expected_signature = "TJVA95OrM7E2cBab30RMHrHDcEfxjoYZgeFONFh7HgQ";
+
+        let key = HmacKey::new(Hs256, b"secret")?;
+        assert_eq!(SigningAlgorithm::algorithm_type(&key), Hs256);
+        assert_eq!(
+            SigningAlgorithm::sign(&key, header, claims)?,
+            expected_signature
+        );
+        assert!(VerifyingAlgorithm::verify(
+            &key,
+            header,
+            claims,
+            expected_signature
+        )?);
+
+        match HmacKey::new(Rs256, b"secret") {
+            Err(Error::UnsupportedAlgorithm(Rs256)) => Ok(()),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("HMAC keys can not be used for RS256"),
+        }
+    }
+
+    #[test]
+    fn rs256() -> Result<(), Error> {
+        // Signed by another implementation
+        const RS256_SIGNATURE: &str = "fuYOP3a8uJ7fjfofLSCtCfhq6UakywDWX1M3rNWWbwZYtir6CJ-7YVM7VqcWq-iFlMCDPhh9B_-sWHTiQHD3CZ4Ctikmsaz2JrQmyjZFdJ2IZzc89pYwHlip3aoOr4GRmi2aTrDMucPHy-r29MI4jGn_uXwAEGLVV3XqsipoiHQnWyTRP2Mqvg7_oRBnNVRc2PXsKXj8xBkW8DR6wK9IfFtk_wTHNk82up_Obthbgs_RmDyNZqfLgVDxjzkbWID6nVcwoGN11mwPpmMDn0K8av99lQ_JDo2H0uNyHUffOEnABZEXZfKO2cKsHwTSPq0HT7VR5smrY2VzFtFw4bF4ow";
+
+        let der = pem_body(include_str!("../../test/rs2048-private.pem"));
+        let key_pair = RsaKeyPair::from_der(&der)?;
+        let public_key = PublicKeyComponents::from(key_pair.public());
+
+        let signing_key = RsaKeyPairWithAlgorithm::new(Rs256, key_pair)?;
+        let verifying_key = PublicKeyWithAlgorithm::from_rsa_components(Rs256, public_key)?;
+
+        let header = AlgOnly(Rs256).to_base64()?;
+        assert_eq!(signing_key.sign(&header, CLAIMS)?, RS256_SIGNATURE);
+        assert!(verifying_key.verify(&header, CLAIMS, RS256_SIGNATURE)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ps256() -> Result<(), Error> {
+        // Signed by another implementation
+        const PS256_SIGNATURE: &str = "ZVQDoU3dZqeJFLRunR02y1aTCGRmFweeGfY-bbS9_oTS10jqEEGfAtANu0moQQJzi-m3ydfS2W5yEynln_3qYpaljTibeMj6DYwq7OJgyBI900L3DVWD5G0MbPfQ2IloN7rbews9ALAvHzJ0c6I5VPRfIJrg3Fwi-09ltY84uPSd4w4LSHyUfq8v2v_4qNQJYtlBeK1ZkbzmAaA6E5nVSe3m2SLlZA61d4f8M-n6kBbhVF7R5dK03omMiaIIQchhRhRnlk4Su9_uDORE2LtC0VK0pa-HmVAKpjmY3OaGLHknuT26O-_uGg3x1k9-fdi2FSEYSSrt92ok6LgFfgkHBQ";
+
+        let der = pem_body(include_str!("../../test/rs2048-private.pem"));
+        let key_pair = RsaKeyPair::from_der(&der)?;
+        let public_key = PublicKeyComponents::from(key_pair.public());
+
+        let signing_key = RsaKeyPairWithAlgorithm::new(Ps256, key_pair)?;
+        let verifying_key = PublicKeyWithAlgorithm::from_rsa_components(Ps256, public_key)?;
+
+        let header = AlgOnly(Ps256).to_base64()?;
+        assert!(verifying_key.verify(&header, CLAIMS, PS256_SIGNATURE)?);
+
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        Ok(())
+    }
+
+    #[test]
+    fn es256() -> Result<(), Error> {
+        // Signed by another implementation
+        const ES256_SIGNATURE: &str = "AGafEbu80OpWMQ2OXIF4uFod28FDDaS33Ss6Ux606SXIltRQkjaY2tuXYaCgQDUKorhKI_s31ed3O9YQPt9jDQ";
+
+        let pkcs8 = pem_body(include_str!("../../test/es256-private-pkcs8.pem"));
+        let signing_key = EcdsaKeyPairWithAlgorithm::from_pkcs8(Es256, &pkcs8)?;
+        let public_key = signing_key.key_pair().public_key().as_ref().to_vec();
+        let verifying_key = PublicKeyWithAlgorithm::new(Es256, public_key)?;
+
+        let header = AlgOnly(Es256).to_base64()?;
+        assert!(verifying_key.verify(&header, CLAIMS, ES256_SIGNATURE)?);
+
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        Ok(())
+    }
+
+    #[test]
+    fn es384() -> Result<(), Error> {
+        let pkcs8 = pem_body(include_str!("../../test/es384-private-pkcs8.pem"));
+        let signing_key = EcdsaKeyPairWithAlgorithm::from_pkcs8(Es384, &pkcs8)?;
+        let public_key = signing_key.key_pair().public_key().as_ref().to_vec();
+        let verifying_key = PublicKeyWithAlgorithm::new(Es384, public_key)?;
+
+        let header = AlgOnly(Es384).to_base64()?;
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ecdsa_key_for_wrong_curve() {
+        let pkcs8 = pem_body(include_str!("../../test/es384-private-pkcs8.pem"));
+        match EcdsaKeyPairWithAlgorithm::from_pkcs8(Es256, &pkcs8) {
+            Err(Error::RingKeyRejected(_)) => (),
+            Ok(_) => panic!("ES384 key should not be accepted for ES256"),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ed25519() -> Result<(), Error> {
+        // RFC 8037 Appendix A.4
+        const HEADER: &str = "eyJhbGciOiJFZERTQSJ9";
+        const PAYLOAD: &str = "RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc";
+        const SIGNATURE: &str =
+            "hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+
+        let pkcs8 = pem_body(include_str!("../../test/ed25519-private.pem"));
+        let signing_key = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&pkcs8)?;
+        let public_key = signing_key.public_key().as_ref().to_vec();
+        let verifying_key = PublicKeyWithAlgorithm::new(EdDsa, public_key)?;
+
+        assert_eq!(
+            SigningAlgorithm::sign(&signing_key, HEADER, PAYLOAD)?,
+            SIGNATURE
+        );
+        assert!(verifying_key.verify(HEADER, PAYLOAD, SIGNATURE)?);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        match PublicKeyWithAlgorithm::new(Es512, vec![]) {
+            Err(Error::UnsupportedAlgorithm(Es512)) => (),
+            Ok(_) => panic!("ES512 is not supported by ring"),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+        }
+
+        match PublicKeyWithAlgorithm::new(Rs256, vec![]) {
+            Err(Error::InvalidKey(_)) => (),
+            Ok(_) => panic!("RSA keys are created from their components"),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+        }
+    }
+
+    fn jwk(name: &str, kind: &str) -> Jwk {
+        let json = std::fs::read_to_string(format!("test/{}-{}.jwk", name, kind)).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn jwk_conversions() -> Result<(), Error> {
+        let mut rs256_private = jwk("rs2048", "private");
+        let mut rs256_public = jwk("rs2048", "public");
+        rs256_private.algorithm = Some(Rs256);
+        rs256_public.algorithm = Some(Rs256);
+        let signing_key = RsaKeyPairWithAlgorithm::try_from(&rs256_private)?;
+        let verifying_key = PublicKeyWithAlgorithm::try_from(&rs256_public)?;
+        let header = AlgOnly(Rs256).to_base64()?;
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+
+        // The public key matches the one parsed by ring from the PEM file
+        let der = pem_body(include_str!("../../test/rs2048-private.pem"));
+        let key_pair = RsaKeyPair::from_der(&der)?;
+        let components = PublicKeyComponents::from(key_pair.public());
+        let pem_key = PublicKeyWithAlgorithm::from_rsa_components(Rs256, components)?;
+        assert!(pem_key.verify(&header, CLAIMS, &signature)?);
+
+        for (name, algorithm) in [("es256", Es256), ("es384", Es384)].iter() {
+            let signing_key = EcdsaKeyPairWithAlgorithm::try_from(&jwk(name, "private"))?;
+            let verifying_key = PublicKeyWithAlgorithm::try_from(&jwk(name, "public"))?;
+            let header = AlgOnly(algorithm.clone()).to_base64()?.into_owned();
+            let signature = signing_key.sign(&header, CLAIMS)?;
+            assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        let signing_key = Ed25519KeyPair::try_from(&jwk("ed25519", "private"))?;
+        let verifying_key = PublicKeyWithAlgorithm::try_from(&jwk("ed25519", "public"))?;
+        let header = AlgOnly(EdDsa).to_base64()?;
+        let signature = SigningAlgorithm::sign(&signing_key, &header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+
+        let hmac_jwk: Jwk = serde_json::from_str(r#"{"kty":"oct","alg":"HS256","k":"c2VjcmV0"}"#)?;
+        let key = HmacKey::try_from(&hmac_jwk)?;
+        assert_eq!(
+            SigningAlgorithm::sign(&key, "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9", CLAIMS)?,
+            "TJVA95OrM7E2cBab30RMHrHDcEfxjoYZgeFONFh7HgQ"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn jwk_conversions_check_key() {
+        match PublicKeyWithAlgorithm::try_from(&jwk("rs2048", "public")) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("An RSA JWK without alg should be rejected"),
+        }
+
+        match PublicKeyWithAlgorithm::try_from(&jwk("ed448", "public")) {
+            Err(Error::InvalidKey(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Ed448 is not supported by ring"),
+        }
+
+        match EcdsaKeyPairWithAlgorithm::try_from(&jwk("es512", "private")) {
+            Err(Error::UnsupportedAlgorithm(Es512)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("ES512 is not supported by ring"),
+        }
+
+        let mut mismatched = jwk("es256", "private");
+        if let KeyParameters::EllipticCurve(ref mut parameters) = mismatched.parameters {
+            parameters.private_key.as_mut().unwrap()[31] ^= 1;
+        }
+        match EcdsaKeyPairWithAlgorithm::try_from(&mismatched) {
+            Err(Error::RingKeyRejected(_)) => (),
+            Err(other) => panic!("Wrong error type: {:?}", other),
+            Ok(_) => panic!("Private key does not match the public key"),
+        }
+    }
+
+    #[test]
+    fn jwk_export() -> Result<(), Error> {
+        use crate::jwk::ToJwk;
+
+        let der = pem_body(include_str!("../../test/rs2048-private.pem"));
+        let key_pair = RsaKeyPair::from_der(&der)?;
+        let public_key = PublicKeyComponents::from(key_pair.public());
+        let signing_key = RsaKeyPairWithAlgorithm::new(Ps256, key_pair)?;
+        let verifying_key = PublicKeyWithAlgorithm::from_rsa_components(Ps256, public_key)?;
+        let exported = verifying_key.to_jwk()?;
+        assert_eq!(exported.parameters, jwk("rs2048", "public").parameters);
+        assert_eq!(exported.algorithm, Some(Ps256));
+        assert_eq!(signing_key.to_jwk()?, exported);
+
+        for (name, algorithm) in [("es256", Es256), ("es384", Es384)].iter() {
+            let pkcs8 = pem_body(
+                &std::fs::read_to_string(format!("test/{}-private-pkcs8.pem", name)).unwrap(),
+            );
+            let signing_key = EcdsaKeyPairWithAlgorithm::from_pkcs8(algorithm.clone(), &pkcs8)?;
+            let exported = signing_key.to_jwk()?;
+            assert_eq!(exported.parameters, jwk(name, "public").parameters);
+            assert_eq!(exported.algorithm.as_ref(), Some(algorithm));
+        }
+
+        let pkcs8 = pem_body(include_str!("../../test/ed25519-private.pem"));
+        let signing_key = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&pkcs8)?;
+        assert_eq!(
+            signing_key.to_jwk()?.parameters,
+            jwk("ed25519", "public").parameters
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn generated_keys() -> Result<(), Error> {
+        use crate::algorithm::ring::{generate_key, ED25519_LAYOUT, P256_LAYOUT, P384_LAYOUT};
+        use crate::jwk::ToJwk;
+
+        for algorithm in [Es256, Es384, EdDsa].iter() {
+            let key = generate_key(algorithm.clone())?;
+            assert_eq!(key.algorithm_type(), *algorithm);
+            assert_eq!(key.to_jwk()?.thumbprint(), key.key_id);
+            assert!(!key.to_jwk()?.is_private());
+
+            let header = AlgOnly(algorithm.clone()).to_base64()?.into_owned();
+            let signature = key.signing_key.sign(&header, CLAIMS)?;
+            assert!(key.verifying_key.verify(&header, CLAIMS, &signature)?);
+
+            // The exports describe the same key pair
+            let private_jwk = key.to_private_jwk()?;
+            let public_key = PublicKeyWithAlgorithm::try_from(&key.to_jwk()?)?;
+            let private_pem = String::from_utf8(key.private_key_to_pem()?).unwrap();
+            let public_pem = String::from_utf8(key.public_key_to_pem()?).unwrap();
+            assert!(public_pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+            let signature = if *algorithm == EdDsa {
+                let from_jwk = Ed25519KeyPair::try_from(&private_jwk)?;
+                let from_pem = Ed25519KeyPair::from_pkcs8(&pem_body(&private_pem))?;
+                assert_eq!(
+                    from_jwk.public_key().as_ref(),
+                    from_pem.public_key().as_ref()
+                );
+                assert!(pem_body(&public_pem).ends_with(from_pem.public_key().as_ref()));
+                SigningAlgorithm::sign(&from_jwk, &header, CLAIMS)?
+            } else {
+                let from_jwk = EcdsaKeyPairWithAlgorithm::try_from(&private_jwk)?;
+                let from_pem = EcdsaKeyPairWithAlgorithm::from_pkcs8(
+                    algorithm.clone(),
+                    &pem_body(&private_pem),
+                )?;
+                let public_key = from_pem.key_pair().public_key().as_ref();
+                assert_eq!(from_jwk.key_pair().public_key().as_ref(), public_key);
+                assert!(pem_body(&public_pem).ends_with(public_key));
+                from_jwk.sign(&header, CLAIMS)?
+            };
+            assert!(public_key.verify(&header, CLAIMS, &signature)?);
+        }
+
+        // The public keys have the same encoding as the test keys
+        let layouts = [
+            ("es256", &P256_LAYOUT),
+            ("es384", &P384_LAYOUT),
+            ("ed25519", &ED25519_LAYOUT),
+        ];
+        for (name, layout) in layouts.iter() {
+            let pem = std::fs::read_to_string(format!("test/{}-public.pem", name)).unwrap();
+            assert!(pem_body(&pem).starts_with(layout.spki_prefix));
+        }
+
+        match generate_key(Rs256) {
+            Err(Error::UnsupportedAlgorithm(Rs256)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other.map(|key| key.key_id)),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! RustCrypto implementations of signing and verifying algorithms.
-//! According to that organization, only hmac is safely implemented at the
-//! moment.
+//! HMAC is always available. RSA is available with the `rsa` feature, see the
+//! [rsa](rsa/index.html) module. ECDSA is available with the `p256`, `p384`,
+//! `p521` and `k256` features, see the [ecdsa](ecdsa/index.html) module.
 
 use digest::{
     block_buffer::Eager,
@@ -10,10 +11,19 @@ use digest::{
     HashMarker,
 };
 use hmac::{Hmac, Mac};
+use std::convert::TryFrom;
 
+use crate::algorithm::generate::GeneratedKey;
 use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
 use crate::error::Error;
+use crate::jwk::Jwk;
 use crate::SEPARATOR;
+
+#[cfg(any(feature = "p256", feature = "p384", feature = "p521", feature = "k256"))]
+pub mod ecdsa;
+#[cfg(feature = "rsa")]
+pub mod rsa;
+
 /// A trait used to make the implementation of `SigningAlgorithm` and
 /// `VerifyingAlgorithm` easier.
 /// RustCrypto crates tend to have algorithm types defined at the type level,
@@ -53,10 +63,14 @@ where
     }
 
     fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+        let signature = self.sign_bytes(header, claims)?;
+        Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
         let hmac = get_hmac_with_data(self, header, claims);
         let mac_result = hmac.finalize();
-        let code = mac_result.into_bytes();
-        Ok(base64::encode_config(&code, base64::URL_SAFE_NO_PAD))
+        Ok(mac_result.into_bytes().to_vec())
     }
 }
 
@@ -83,6 +97,43 @@ where
     }
 }
 
+impl<D> TryFrom<&Jwk> for Hmac<D>
+where
+    D: CoreProxy + TypeLevelAlgorithmType,
+    D::Core: HashMarker
+        + BufferKindUser<BufferKind = Eager>
+        + FixedOutputCore
+        + digest::Reset
+        + Default
+        + Clone,
+    <D::Core as BlockSizeUser>::BlockSize: IsLess<U256>,
+    Le<<D::Core as BlockSizeUser>::BlockSize, U256>: NonZero,
+{
+    type Error = Error;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+        jwk.check_algorithm(&D::algorithm_type())?;
+        let parameters = jwk.octet_sequence_parameters()?;
+        Ok(Hmac::new_from_slice(&parameters.key_value)?)
+    }
+}
+
+/// Generate an RSA or ECDSA key with the RustCrypto crates of the enabled
+/// features. Other algorithms return `Error::UnsupportedAlgorithm`.
+pub(crate) fn generate_key(algorithm: AlgorithmType) -> Result<GeneratedKey, Error> {
+    #[cfg(feature = "rsa")]
+    let algorithm = match rsa::generate_key(algorithm) {
+        Err(Error::UnsupportedAlgorithm(algorithm)) => algorithm,
+        result => return result,
+    };
+    #[cfg(any(feature = "p256", feature = "p384", feature = "p521", feature = "k256"))]
+    let algorithm = match ecdsa::generate_key(algorithm) {
+        Err(Error::UnsupportedAlgorithm(algorithm)) => algorithm,
+        result => return result,
+    };
+    Err(Error::UnsupportedAlgorithm(algorithm))
+}
+
 fn get_hmac_with_data<D>(hmac: &Hmac<D>, header: &str, claims: &str) -> Hmac<D>
 where
     D: CoreProxy,
@@ -123,6 +174,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn sign_bytes() -> Result<(), Error> {
+        let header = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+        let claims = "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+        let expected_signature = "TJVA95OrM7E2cBab30RMHrHDcEfxjoYZgeFONFh7HgQ";
+
+        let signer: Hmac<Sha256> = Hmac::new_from_slice(b"secret")?;
+        let computed_signature = signer.sign_bytes(header, claims)?;
+
+        assert_eq!(
+            computed_signature,
+            base64::decode_config(expected_signature, base64::URL_SAFE_NO_PAD)?
+        );
+        Ok(())
+    }
+
     #[test]
     pub fn verify() -> Result<(), Error> {
         let header = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
@@ -0,0 +1,364 @@
+//! Pure Rust RSA support through the rsa crate, enabled with the `rsa`
+//! feature. PKCS #1 v1.5 keys from `rsa::pkcs1v15` are used for `RS256`,
+//! `RS384` and `RS512`, and RSASSA-PSS keys from `rsa::pss` are used for
+//! `PS256`, `PS384` and `PS512`. The digest is chosen by the key's type
+//! parameter. PSS keys created with `new` use a salt as long as the digest, as
+//! required by [JWA](https://tools.ietf.org/html/rfc7518#section-3.5).
+//! ## Examples
+//! ```
+//! use rsa::pkcs1v15::VerifyingKey;
+//! use rsa::pkcs8::DecodePublicKey;
+//! use rsa::RsaPublicKey;
+//! use sha2::Sha256;
+//!
+//! let pem = include_str!("../../../test/rs2048-public.pem");
+//! let rs256_public_key: VerifyingKey<Sha256> =
+//!     VerifyingKey::new(RsaPublicKey::from_public_key_pem(pem).unwrap());
+//! ```
+
+use std::convert::TryFrom;
+
+use digest::Digest;
+use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::rand_core::OsRng;
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+use rsa::{pkcs1v15, pss, BigUint, RsaPrivateKey, RsaPublicKey};
+use sha2::{Sha256, Sha384, Sha512};
+use signature::{DigestVerifier, RandomizedDigestSigner, SignatureEncoding};
+
+use crate::algorithm::generate::GeneratedKey;
+use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+use crate::error::Error;
+use crate::jwk::{private_parameter, Jwk, KeyParameters, RsaParameters, ToJwk};
+use crate::SEPARATOR;
+
+macro_rules! rsa_algorithm {
+    ($padding: ident, $digest: ty, $algorithm_type: expr) => {
+        impl SigningAlgorithm for $padding::SigningKey<$digest> {
+            fn algorithm_type(&self) -> AlgorithmType {
+                $algorithm_type
+            }
+
+            fn sign(&self, header: &str, claims: &str) -> Result<String, Error> {
+                let signature = self.sign_bytes(header, claims)?;
+                Ok(base64::encode_config(&signature, base64::URL_SAFE_NO_PAD))
+            }
+
+            fn sign_bytes(&self, header: &str, claims: &str) -> Result<Vec<u8>, Error> {
+                let digest = get_digest_with_data::<$digest>(header, claims);
+                let signature: $padding::Signature =
+                    self.try_sign_digest_with_rng(&mut OsRng, digest)?;
+                Ok(signature.to_vec())
+            }
+        }
+
+        impl VerifyingAlgorithm for $padding::VerifyingKey<$digest> {
+            fn algorithm_type(&self) -> AlgorithmType {
+                $algorithm_type
+            }
+
+            fn verify_bytes(
+                &self,
+                header: &str,
+                claims: &str,
+                signature: &[u8],
+            ) -> Result<bool, Error> {
+                let signature = $padding::Signature::try_from(signature)?;
+                let digest = get_digest_with_data::<$digest>(header, claims);
+                Ok(self.verify_digest(digest, &signature).is_ok())
+            }
+        }
+
+        impl TryFrom<&Jwk> for $padding::SigningKey<$digest> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                jwk.check_algorithm(&$algorithm_type)?;
+                Ok($padding::SigningKey::new(private_key_from_jwk(jwk)?))
+            }
+        }
+
+        impl TryFrom<&Jwk> for $padding::VerifyingKey<$digest> {
+            type Error = Error;
+
+            fn try_from(jwk: &Jwk) -> Result<Self, Error> {
+                jwk.check_algorithm(&$algorithm_type)?;
+                Ok($padding::VerifyingKey::new(public_key_from_jwk(jwk)?))
+            }
+        }
+
+        impl ToJwk for $padding::SigningKey<$digest> {
+            fn to_jwk(&self) -> Result<Jwk, Error> {
+                let private_key: &RsaPrivateKey = self.as_ref();
+                Ok(public_key_to_jwk(private_key, $algorithm_type))
+            }
+        }
+
+        impl ToJwk for $padding::VerifyingKey<$digest> {
+            fn to_jwk(&self) -> Result<Jwk, Error> {
+                let public_key: &RsaPublicKey = self.as_ref();
+                Ok(public_key_to_jwk(public_key, $algorithm_type))
+            }
+        }
+    };
+}
+
+rsa_algorithm!(pkcs1v15, Sha256, AlgorithmType::Rs256);
+rsa_algorithm!(pkcs1v15, Sha384, AlgorithmType::Rs384);
+rsa_algorithm!(pkcs1v15, Sha512, AlgorithmType::Rs512);
+rsa_algorithm!(pss, Sha256, AlgorithmType::Ps256);
+rsa_algorithm!(pss, Sha384, AlgorithmType::Ps384);
+rsa_algorithm!(pss, Sha512, AlgorithmType::Ps512);
+
+fn public_key_from_jwk(jwk: &Jwk) -> Result<RsaPublicKey, Error> {
+    let parameters = jwk.rsa_parameters()?;
+    RsaPublicKey::new(
+        BigUint::from_bytes_be(&parameters.modulus),
+        BigUint::from_bytes_be(&parameters.exponent),
+    )
+    .map_err(|e| Error::InvalidKey(e.to_string()))
+}
+
+fn public_key_to_jwk(key: &impl PublicKeyParts, algorithm_type: AlgorithmType) -> Jwk {
+    let parameters = RsaParameters::new(key.n().to_bytes_be(), key.e().to_bytes_be());
+    Jwk::signature_key(KeyParameters::Rsa(parameters), algorithm_type)
+}
+
+fn private_key_from_jwk(jwk: &Jwk) -> Result<RsaPrivateKey, Error> {
+    let parameters = jwk.rsa_parameters()?;
+    let private_exponent = private_parameter(&parameters.private_exponent, "d")?;
+    let first_prime = private_parameter(&parameters.first_prime, "p")?;
+    let second_prime = private_parameter(&parameters.second_prime, "q")?;
+
+    let key = RsaPrivateKey::from_components(
+        BigUint::from_bytes_be(&parameters.modulus),
+        BigUint::from_bytes_be(&parameters.exponent),
+        BigUint::from_bytes_be(private_exponent),
+        vec![
+            BigUint::from_bytes_be(first_prime),
+            BigUint::from_bytes_be(second_prime),
+        ],
+    )
+    .and_then(|key| key.validate().map(|()| key))
+    .map_err(|e| Error::InvalidKey(e.to_string()))?;
+    Ok(key)
+}
+
+/// Generate a 2048 bit key for `GeneratedKey::generate`.
+pub(crate) fn generate_key(algorithm: AlgorithmType) -> Result<GeneratedKey, Error> {
+    match algorithm {
+        AlgorithmType::Rs256
+        | AlgorithmType::Rs384
+        | AlgorithmType::Rs512
+        | AlgorithmType::Ps256
+        | AlgorithmType::Ps384
+        | AlgorithmType::Ps512 => (),
+        other => return Err(Error::UnsupportedAlgorithm(other)),
+    }
+
+    let invalid_key = |e: &dyn std::fmt::Display| Error::InvalidKey(e.to_string());
+    let private_key = RsaPrivateKey::new(&mut OsRng, 2048).map_err(|e| invalid_key(&e))?;
+    let public_jwk = public_key_to_jwk(&private_key, algorithm);
+    let encode = |value: &BigUint| value.to_bytes_be();
+    let private_jwk = Jwk {
+        parameters: KeyParameters::Rsa(RsaParameters {
+            private_exponent: Some(encode(private_key.d())),
+            first_prime: private_key.primes().first().map(encode),
+            second_prime: private_key.primes().get(1).map(encode),
+            first_prime_exponent: private_key.dp().map(encode),
+            second_prime_exponent: private_key.dq().map(encode),
+            coefficient: private_key.crt_coefficient().as_ref().map(encode),
+            ..public_jwk.rsa_parameters()?.clone()
+        }),
+        ..public_jwk.clone()
+    };
+    let private_key_pem = private_key
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|e| invalid_key(&e))?;
+    let public_key_pem = private_key
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| invalid_key(&e))?;
+
+    Ok(GeneratedKey::from_key_pair(
+        private_jwk.to_signing_key()?,
+        public_jwk.to_verifying_key()?,
+        private_jwk,
+        public_jwk,
+        private_key_pem.as_bytes().to_vec(),
+        public_key_pem.into_bytes(),
+    ))
+}
+
+fn get_digest_with_data<D: Digest>(header: &str, claims: &str) -> D {
+    let mut digest = D::new();
+    digest.update(header.as_bytes());
+    digest.update(SEPARATOR.as_bytes());
+    digest.update(claims.as_bytes());
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use rsa::pkcs1::DecodeRsaPrivateKey;
+    use rsa::pkcs8::DecodePublicKey;
+    use rsa::{pkcs1v15, pss, RsaPrivateKey, RsaPublicKey};
+    use sha2::{Sha256, Sha384, Sha512};
+
+    use crate::algorithm::AlgorithmType::*;
+    use crate::algorithm::{SigningAlgorithm, VerifyingAlgorithm};
+    use crate::error::Error;
+    use crate::header::PrecomputedAlgorithmOnlyHeader as AlgOnly;
+    use crate::ToBase64;
+
+    // {"sub":"1234567890","name":"John Doe","admin":true}
+    const CLAIMS: &str = "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiYWRtaW4iOnRydWV9";
+
+    // Same signature as the OpenSSL RS256 test
+    const RS256_SIGNATURE: &str =
+    "cQsAHF2jHvPGFP5zTD8BgoJrnzEx6JNQCpupebWLFnOc2r_punDDTylI6Ia4JZNkvy2dQP-7W-DEbFQ3oaarHsDndqUgwf9iYlDQxz4Rr2nEZX1FX0-FMEgFPeQpdwveCgjtTYUbVy37ijUySN_rW-xZTrsh_Ug-ica8t-zHRIw";
+
+    // Same signature as the OpenSSL PS256 test
+    const PS256_SIGNATURE: &str =
+    "ZVQDoU3dZqeJFLRunR02y1aTCGRmFweeGfY-bbS9_oTS10jqEEGfAtANu0moQQJzi-m3ydfS2W5yEynln_3qYpaljTibeMj6DYwq7OJgyBI900L3DVWD5G0MbPfQ2IloN7rbews9ALAvHzJ0c6I5VPRfIJrg3Fwi-09ltY84uPSd4w4LSHyUfq8v2v_4qNQJYtlBeK1ZkbzmAaA6E5nVSe3m2SLlZA61d4f8M-n6kBbhVF7R5dK03omMiaIIQchhRhRnlk4Su9_uDORE2LtC0VK0pa-HmVAKpjmY3OaGLHknuT26O-_uGg3x1k9-fdi2FSEYSSrt92ok6LgFfgkHBQ";
+
+    // Some of the test keys are not wrapped at 64 columns, which the strict
+    // PEM parser rejects, so decode the body directly.
+    fn pem_body(pem: &str) -> Vec<u8> {
+        let body: String = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        base64::decode(body).unwrap()
+    }
+
+    fn private_key(pem: &str) -> RsaPrivateKey {
+        RsaPrivateKey::from_pkcs1_der(&pem_body(pem)).unwrap()
+    }
+
+    fn public_key(pem: &str) -> RsaPublicKey {
+        RsaPublicKey::from_public_key_der(&pem_body(pem)).unwrap()
+    }
+
+    #[test]
+    fn rs256_sign() -> Result<(), Error> {
+        let key = private_key(include_str!("../../../test/rs256-private.pem"));
+        let algorithm = pkcs1v15::SigningKey::<Sha256>::new(key);
+
+        let result = algorithm.sign(&AlgOnly(Rs256).to_base64()?, CLAIMS)?;
+        assert_eq!(result, RS256_SIGNATURE);
+        Ok(())
+    }
+
+    #[test]
+    fn rs256_verify() -> Result<(), Error> {
+        let key = public_key(include_str!("../../../test/rs256-public.pem"));
+        let algorithm = pkcs1v15::VerifyingKey::<Sha256>::new(key);
+
+        let verification_result =
+            algorithm.verify(&AlgOnly(Rs256).to_base64()?, CLAIMS, RS256_SIGNATURE)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn rs256_verify_with_wrong_key() -> Result<(), Error> {
+        let key = public_key(include_str!("../../../test/rs256-public-2.pem"));
+        let algorithm = pkcs1v15::VerifyingKey::<Sha256>::new(key);
+
+        let verification_result =
+            algorithm.verify(&AlgOnly(Rs256).to_base64()?, CLAIMS, RS256_SIGNATURE)?;
+        assert!(!verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn ps256_verify() -> Result<(), Error> {
+        let key = public_key(include_str!("../../../test/rs2048-public.pem"));
+        let algorithm = pss::VerifyingKey::<Sha256>::new(key);
+
+        let verification_result =
+            algorithm.verify(&AlgOnly(Ps256).to_base64()?, CLAIMS, PS256_SIGNATURE)?;
+        assert!(verification_result);
+        Ok(())
+    }
+
+    #[test]
+    fn rs_roundtrip() -> Result<(), Error> {
+        let private = private_key(include_str!("../../../test/rs2048-private.pem"));
+        let public = public_key(include_str!("../../../test/rs2048-public.pem"));
+
+        roundtrip(
+            &pkcs1v15::SigningKey::<Sha256>::new(private.clone()),
+            &pkcs1v15::VerifyingKey::<Sha256>::new(public.clone()),
+        )?;
+        roundtrip(
+            &pkcs1v15::SigningKey::<Sha384>::new(private.clone()),
+            &pkcs1v15::VerifyingKey::<Sha384>::new(public.clone()),
+        )?;
+        roundtrip(
+            &pkcs1v15::SigningKey::<Sha512>::new(private),
+            &pkcs1v15::VerifyingKey::<Sha512>::new(public),
+        )
+    }
+
+    #[test]
+    fn ps_roundtrip() -> Result<(), Error> {
+        let private = private_key(include_str!("../../../test/rs2048-private.pem"));
+        let public = public_key(include_str!("../../../test/rs2048-public.pem"));
+
+        roundtrip(
+            &pss::SigningKey::<Sha256>::new(private.clone()),
+            &pss::VerifyingKey::<Sha256>::new(public.clone()),
+        )?;
+        roundtrip(
+            &pss::SigningKey::<Sha384>::new(private.clone()),
+            &pss::VerifyingKey::<Sha384>::new(public.clone()),
+        )?;
+        roundtrip(
+            &pss::SigningKey::<Sha512>::new(private),
+            &pss::VerifyingKey::<Sha512>::new(public),
+        )
+    }
+
+    #[test]
+    fn generated_key() -> Result<(), Error> {
+        use crate::algorithm::rust_crypto::rsa::generate_key;
+        use crate::jwk::ToJwk;
+        use rsa::pkcs8::DecodePrivateKey;
+
+        let key = generate_key(Ps256)?;
+        assert_eq!(key.algorithm_type(), Ps256);
+        assert_eq!(key.to_jwk()?.thumbprint(), key.key_id);
+        assert!(key.to_private_jwk()?.is_private());
+        assert!(!key.to_jwk()?.is_private());
+
+        let private_pem = String::from_utf8(key.private_key_to_pem()?).unwrap();
+        let public_pem = String::from_utf8(key.public_key_to_pem()?).unwrap();
+        let private_key = RsaPrivateKey::from_pkcs8_pem(&private_pem).unwrap();
+        let public_key = RsaPublicKey::from_public_key_pem(&public_pem).unwrap();
+        roundtrip(&key.signing_key, &key.verifying_key)?;
+        roundtrip(
+            &pss::SigningKey::<Sha256>::new(private_key),
+            &key.verifying_key,
+        )?;
+        roundtrip(
+            &key.signing_key,
+            &pss::VerifyingKey::<Sha256>::new(public_key),
+        )
+    }
+
+    fn roundtrip(
+        signing_key: &impl SigningAlgorithm,
+        verifying_key: &impl VerifyingAlgorithm,
+    ) -> Result<(), Error> {
+        let algorithm_type = signing_key.algorithm_type();
+        assert_eq!(algorithm_type, verifying_key.algorithm_type());
+
+        let header = AlgOnly(algorithm_type);
+        let header = header.to_base64()?;
+        let signature = signing_key.sign(&header, CLAIMS)?;
+        assert!(verifying_key.verify(&header, CLAIMS, &signature)?);
+        Ok(())
+    }
+}
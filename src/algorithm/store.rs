@@ -1,12 +1,79 @@
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::iter;
+
+use crate::algorithm::AlgorithmType;
+use crate::error::Error;
+
+/// The keys of a store with their ids, as listed by `Store::candidates`.
+pub type Candidates<'a, A> = Box<dyn Iterator<Item = (Option<&'a str>, &'a A)> + 'a>;
 
 /// A store of keys that can be retrieved by key id.
 pub trait Store {
     type Algorithm: ?Sized;
 
     fn get(&self, key_id: &str) -> Option<&Self::Algorithm>;
+
+    /// Get the key for a token signed with `algorithm_type`. Stores that can
+    /// hold several keys with the same id use the algorithm to choose between
+    /// them. By default the algorithm is ignored.
+    fn get_with_algorithm(
+        &self,
+        key_id: &str,
+        _algorithm_type: &AlgorithmType,
+    ) -> Option<&Self::Algorithm> {
+        self.get(key_id)
+    }
+
+    /// Get the key with `key_id` for signing. Stores whose keys may only
+    /// sign at certain times reject the other keys here. By default this is
+    /// the key returned by `get`.
+    fn signing_key(&self, key_id: &str) -> Result<&Self::Algorithm, Error> {
+        self.get(key_id)
+            .ok_or_else(|| Error::NoKeyWithKeyId(key_id.to_owned()))
+    }
+
+    /// The `kid` written into the header when signing claims with the key
+    /// stored under `key_id`. By default this is `key_id` itself.
+    fn header_key_id<'a>(&'a self, key_id: &'a str) -> Result<Cow<'a, str>, Error> {
+        Ok(Cow::Borrowed(key_id))
+    }
+
+    /// Every key that may verify tokens, with its id if it has one. These
+    /// are the keys tried for tokens without a `kid`. By default a store has
+    /// no candidates.
+    fn candidates(&self) -> Candidates<'_, Self::Algorithm> {
+        Box::new(iter::empty())
+    }
+}
+
+impl<S: Store> Store for &S {
+    type Algorithm = S::Algorithm;
+
+    fn get(&self, key_id: &str) -> Option<&S::Algorithm> {
+        (**self).get(key_id)
+    }
+
+    fn get_with_algorithm(
+        &self,
+        key_id: &str,
+        algorithm_type: &AlgorithmType,
+    ) -> Option<&S::Algorithm> {
+        (**self).get_with_algorithm(key_id, algorithm_type)
+    }
+
+    fn signing_key(&self, key_id: &str) -> Result<&S::Algorithm, Error> {
+        (**self).signing_key(key_id)
+    }
+
+    fn header_key_id<'a>(&'a self, key_id: &'a str) -> Result<Cow<'a, str>, Error> {
+        (**self).header_key_id(key_id)
+    }
+
+    fn candidates(&self) -> Candidates<'_, S::Algorithm> {
+        (**self).candidates()
+    }
 }
 
 impl<K, A> Store for BTreeMap<K, A>
@@ -18,6 +85,13 @@ where
     fn get(&self, key_id: &str) -> Option<&A> {
         BTreeMap::get(self, key_id)
     }
+
+    fn candidates(&self) -> Candidates<'_, A> {
+        Box::new(
+            self.iter()
+                .map(|(key_id, key)| (Some(key_id.borrow()), key)),
+        )
+    }
 }
 
 impl<K, A> Store for HashMap<K, A>
@@ -29,4 +103,11 @@ where
     fn get(&self, key_id: &str) -> Option<&A> {
         HashMap::get(self, key_id)
     }
+
+    fn candidates(&self) -> Candidates<'_, A> {
+        Box::new(
+            self.iter()
+                .map(|(key_id, key)| (Some(key_id.borrow()), key)),
+        )
+    }
 }
@@ -62,6 +62,16 @@ pub struct StringOrVec {
     multi: Option<Vec<String>>,
 }
 
+impl StringOrVec {
+    /// The audiences, whether the claim is a single string or an array.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.one
+            .iter()
+            .chain(self.multi.iter().flatten())
+            .map(String::as_str)
+    }
+}
+
 struct StringOrVecVisitor;
 
 impl<'de> Visitor<'de> for StringOrVecVisitor {
@@ -110,7 +120,7 @@ impl Serialize for StringOrVec {
         S: Serializer,
     {
         if let Some(o) = &self.one {
-            serializer.serialize_str(&o)
+            serializer.serialize_str(o)
         } else if let Some(multi) = &self.multi {
             let mut seq = serializer.serialize_seq(Some(multi.len()))?;
             for e in multi {
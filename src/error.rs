@@ -5,51 +5,115 @@ use base64::DecodeError;
 use crypto_common::InvalidLength;
 use digest::MacError;
 use serde_json::Error as JsonError;
+use signature::Error as SignatureError;
 
 use self::Error::*;
 use crate::algorithm::AlgorithmType;
+use crate::jwk::KeyOperation;
 
 #[derive(Debug)]
 pub enum Error {
     AlgorithmMismatch(AlgorithmType, AlgorithmType),
+    AlgorithmNotAllowed(AlgorithmType),
+    AudienceNotAllowed,
     Base64(DecodeError),
+    CandidateLimitExceeded(usize),
+    FetchKeySet(String),
     Format,
+    InvalidKey(String),
+    InvalidPassphrase,
     InvalidSignature,
+    IssuerNotAllowed(Option<String>),
     Json(JsonError),
+    KeyExpired,
+    KeyNotYetValid,
+    KeyOperationNotAllowed(KeyOperation),
+    NoActiveKey,
+    NoCandidateKeys,
     NoClaimsComponent,
+    NoExpiration,
     NoHeaderComponent,
     NoKeyId,
     NoKeyWithKeyId(String),
     NoSignatureComponent,
+    Random(rand_core::Error),
     RustCryptoMac(MacError),
     RustCryptoMacKeyLength(InvalidLength),
+    RustCryptoSignature(SignatureError),
+    TokenExpired,
+    TokenNotYetValid,
     TooManyComponents,
+    UnknownIssuer(Option<String>),
+    UnsecuredToken,
+    UnsupportedAlgorithm(AlgorithmType),
+    UnsupportedKeyEncryption(String),
     Utf8(FromUtf8Error),
     #[cfg(feature = "openssl")]
     OpenSsl(openssl::error::ErrorStack),
+    #[cfg(feature = "ring")]
+    Ring(ring::error::Unspecified),
+    #[cfg(feature = "ring")]
+    RingKeyRejected(ring::error::KeyRejected),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
-            AlgorithmMismatch(a, b) => {
+            AlgorithmMismatch(ref a, ref b) => {
                 write!(f, "Expected algorithm type {:?} but found {:?}", a, b)
             }
+            AlgorithmNotAllowed(ref a) => {
+                write!(f, "Algorithm type {:?} is not allowed for the key", a)
+            }
+            AudienceNotAllowed => write!(f, "The token is not intended for an allowed audience"),
+            IssuerNotAllowed(Some(ref issuer)) => {
+                write!(f, "Issuer {} is not allowed to use the key", issuer)
+            }
+            IssuerNotAllowed(None) => write!(f, "Tokens without an issuer can not use the key"),
+            KeyExpired => write!(f, "The key has expired"),
+            KeyNotYetValid => write!(f, "The key is not valid yet"),
+            KeyOperationNotAllowed(ref operation) => {
+                write!(f, "The key may not be used to {}", operation)
+            }
+            NoActiveKey => write!(f, "No key is active for signing"),
+            NoCandidateKeys => write!(f, "No candidate key found for the token"),
+            CandidateLimitExceeded(attempts) => {
+                write!(f, "No key verified the token in {} attempts", attempts)
+            }
+            NoExpiration => write!(f, "The token has no expiration time"),
+            TokenExpired => write!(f, "The token has expired"),
+            TokenNotYetValid => write!(f, "The token is not valid yet"),
+            UnknownIssuer(Some(ref issuer)) => write!(f, "No keys found for issuer {}", issuer),
+            UnknownIssuer(None) => write!(f, "The token has no issuer"),
             NoKeyId => write!(f, "No key id found"),
             NoKeyWithKeyId(ref kid) => write!(f, "Key with key id {} not found", kid),
             NoHeaderComponent => write!(f, "No header component found in token string"),
             NoClaimsComponent => write!(f, "No claims component found in token string"),
             NoSignatureComponent => write!(f, "No signature component found in token string"),
             TooManyComponents => write!(f, "Too many components found in token string"),
+            UnsecuredToken => write!(f, "Unsecured tokens are only allowed when opted in"),
+            UnsupportedAlgorithm(ref a) => write!(f, "Algorithm type {:?} is not supported", a),
+            FetchKeySet(ref msg) => write!(f, "Failed to fetch key set: {}", msg),
             Format => write!(f, "Format"),
+            InvalidKey(ref msg) => write!(f, "Invalid key: {}", msg),
+            InvalidPassphrase => write!(f, "Invalid passphrase for encrypted key"),
+            UnsupportedKeyEncryption(ref msg) => {
+                write!(f, "Unsupported key encryption: {}", msg)
+            }
             InvalidSignature => write!(f, "Invalid signature"),
             Base64(ref x) => write!(f, "{}", x),
+            Random(ref x) => write!(f, "Failed to generate random bytes: {}", x),
             Json(ref x) => write!(f, "{}", x),
             Utf8(ref x) => write!(f, "{}", x),
             RustCryptoMac(ref x) => write!(f, "{}", x),
             RustCryptoMacKeyLength(ref x) => write!(f, "{}", x),
+            RustCryptoSignature(ref x) => write!(f, "{}", x),
             #[cfg(feature = "openssl")]
             OpenSsl(ref x) => write!(f, "{}", x),
+            #[cfg(feature = "ring")]
+            Ring(ref x) => write!(f, "{}", x),
+            #[cfg(feature = "ring")]
+            RingKeyRejected(ref x) => write!(f, "{}", x),
         }
     }
 }
@@ -71,5 +135,11 @@ error_wrap!(JsonError, Json);
 error_wrap!(FromUtf8Error, Utf8);
 error_wrap!(MacError, RustCryptoMac);
 error_wrap!(InvalidLength, RustCryptoMacKeyLength);
+error_wrap!(SignatureError, RustCryptoSignature);
+error_wrap!(rand_core::Error, Random);
 #[cfg(feature = "openssl")]
 error_wrap!(openssl::error::ErrorStack, Error::OpenSsl);
+#[cfg(feature = "ring")]
+error_wrap!(ring::error::Unspecified, Error::Ring);
+#[cfg(feature = "ring")]
+error_wrap!(ring::error::KeyRejected, Error::RingKeyRejected);
@@ -45,7 +45,7 @@ pub struct Header {
 
 impl JoseHeader for Header {
     fn algorithm_type(&self) -> AlgorithmType {
-        self.algorithm
+        self.algorithm.clone()
     }
 
     fn key_id(&self) -> Option<&str> {
@@ -80,8 +80,8 @@ pub struct PrecomputedAlgorithmOnlyHeader(pub AlgorithmType);
 
 impl JoseHeader for PrecomputedAlgorithmOnlyHeader {
     fn algorithm_type(&self) -> AlgorithmType {
-        let PrecomputedAlgorithmOnlyHeader(algorithm_type) = *self;
-        algorithm_type
+        let PrecomputedAlgorithmOnlyHeader(ref algorithm_type) = *self;
+        algorithm_type.clone()
     }
 }
 
@@ -97,10 +97,19 @@ impl ToBase64 for PrecomputedAlgorithmOnlyHeader {
             AlgorithmType::Es256 => "eyJhbGciOiAiRVMyNTYifQ",
             AlgorithmType::Es384 => "eyJhbGciOiAiRVMzODQifQ",
             AlgorithmType::Es512 => "eyJhbGciOiAiRVM1MTIifQ",
+            AlgorithmType::Es256k => "eyJhbGciOiAiRVMyNTZLIn0",
             AlgorithmType::Ps256 => "eyJhbGciOiAiUFMyNTYifQ",
             AlgorithmType::Ps384 => "eyJhbGciOiAiUFMzODQifQ",
             AlgorithmType::Ps512 => "eyJhbGciOiAiUFM1MTIifQ",
+            AlgorithmType::EdDsa => "eyJhbGciOiAiRWREU0EifQ",
             AlgorithmType::None => "eyJhbGciOiAibm9uZSJ9Cg",
+            AlgorithmType::Other(_) => {
+                let header = Header {
+                    algorithm: self.algorithm_type(),
+                    ..Default::default()
+                };
+                return Ok(Cow::Owned(header.to_base64()?.into_owned()));
+            }
         };
 
         Ok(Cow::Borrowed(precomputed_str))
@@ -120,7 +129,7 @@ pub(crate) struct BorrowedKeyHeader<'a> {
 
 impl<'a> JoseHeader for BorrowedKeyHeader<'a> {
     fn algorithm_type(&self) -> AlgorithmType {
-        self.algorithm
+        self.algorithm.clone()
     }
 
     fn key_id(&self) -> Option<&str> {
@@ -152,6 +161,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn unknown_algorithm() -> Result<(), Error> {
+        // {"alg":"XS256","kid":"custom"}
+        let enc = "eyJhbGciOiJYUzI1NiIsImtpZCI6ImN1c3RvbSJ9";
+        let header = Header::from_base64(enc)?;
+
+        assert_eq!(header.algorithm, AlgorithmType::Other("XS256".to_owned()));
+        assert_eq!(header.key_id.unwrap(), "custom");
+        Ok(())
+    }
+
+    #[test]
+    fn write_base64() -> Result<(), Error> {
+        let header = Header {
+            key_id: Some("kid".to_owned()),
+            ..Default::default()
+        };
+        let mut buffer = String::from("prefix.");
+        header.write_base64(&mut buffer)?;
+        assert_eq!(buffer, format!("prefix.{}", header.to_base64()?));
+
+        let precomputed = PrecomputedAlgorithmOnlyHeader(AlgorithmType::Hs256);
+        let mut buffer = String::new();
+        precomputed.write_base64(&mut buffer)?;
+        assert_eq!(buffer, precomputed.to_base64()?);
+        Ok(())
+    }
+
     #[test]
     fn roundtrip() -> Result<(), Error> {
         let header: Header = Default::default();
@@ -172,14 +209,17 @@ mod tests {
             AlgorithmType::Es256,
             AlgorithmType::Es384,
             AlgorithmType::Es512,
+            AlgorithmType::Es256k,
             AlgorithmType::Ps256,
             AlgorithmType::Ps384,
             AlgorithmType::Ps512,
+            AlgorithmType::EdDsa,
             AlgorithmType::None,
+            AlgorithmType::Other("XS256".to_owned()),
         ];
 
         for algorithm in algorithms.iter() {
-            let precomputed = PrecomputedAlgorithmOnlyHeader(*algorithm);
+            let precomputed = PrecomputedAlgorithmOnlyHeader(algorithm.clone());
             let precomputed_str = precomputed.to_base64()?;
 
             let header = Header::from_base64(&*precomputed_str)?;
@@ -0,0 +1,404 @@
+//! Verification of tokens from several issuers, each with its own keys and
+//! validation settings. An `IssuerStore` routes a token on its unverified
+//! `iss` claim to the keys of that issuer, and then on the `kid` of the
+//! header to a key, so issuers can use the same key ids without colliding.
+//! The keys of an issuer only ever verify tokens that claim that issuer.
+//!
+//! After the signature is verified, the registered claims are validated with
+//! the `IssuerSettings` of the issuer.
+//! ```
+//! use hmac::{Hmac, Mac};
+//! use jwt::issuer::{IssuerSettings, IssuerStore, VerifyWithIssuers};
+//! use jwt::{Claims, Error, RegisteredClaims, SignWithStore};
+//! use sha2::Sha256;
+//! use std::collections::BTreeMap;
+//!
+//! # fn try_main() -> Result<(), Error> {
+//! let mut first_keys: BTreeMap<_, Hmac<Sha256>> = BTreeMap::new();
+//! first_keys.insert("key", Hmac::new_from_slice(b"first-secret")?);
+//! let mut second_keys: BTreeMap<_, Hmac<Sha256>> = BTreeMap::new();
+//! second_keys.insert("key", Hmac::new_from_slice(b"second-secret")?);
+//!
+//! let mut issuers = IssuerStore::new();
+//! issuers.insert("https://first.example", first_keys, IssuerSettings::default());
+//! let settings = IssuerSettings {
+//!     audiences: Some(vec!["gateway".to_owned()]),
+//!     ..Default::default()
+//! };
+//! issuers.insert("https://second.example", second_keys, settings);
+//!
+//! let claims = Claims::new(RegisteredClaims {
+//!     issuer: Some("https://first.example".to_owned()),
+//!     ..Default::default()
+//! });
+//! let token = ("key", &claims).sign_with_store(issuers.keys("https://first.example").unwrap())?;
+//! let verified: Claims = token.as_str().verify_with_issuers(&issuers)?;
+//! assert_eq!(verified, claims);
+//! # Ok(())
+//! # }
+//! # try_main().unwrap()
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::algorithm::key_ring::{Clock, SystemClock};
+use crate::algorithm::store::Store;
+use crate::algorithm::{AlgorithmType, VerifyingAlgorithm};
+use crate::claims::RegisteredClaims;
+use crate::error::Error;
+use crate::header::{Header, JoseHeader};
+use crate::token::verified::{CandidateKeys, VerifyWithCandidates, VerifyWithStore};
+use crate::token::{Unverified, Verified};
+use crate::{FromBase64, Token};
+
+/// How the tokens of an issuer are verified and validated. The defaults allow
+/// every algorithm and audience, and require a `kid`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IssuerSettings {
+    /// The algorithms the issuer signs with.
+    pub algorithms: Option<Vec<AlgorithmType>>,
+    /// The `aud` claim must contain one of these audiences.
+    pub audiences: Option<Vec<String>>,
+    /// Tokens without an `exp` claim are rejected.
+    pub require_expiration: bool,
+    /// The clock skew allowed when checking the `exp` and `nbf` claims.
+    pub leeway: Duration,
+    /// Tokens without a `kid` are verified by trying at most this many keys
+    /// of the issuer, as with `CandidateKeys`. If unset, tokens without a
+    /// `kid` are rejected.
+    pub candidate_attempts: Option<usize>,
+}
+
+impl IssuerSettings {
+    /// Check the registered claims of a verified token at `now`.
+    fn validate(&self, claims: &RegisteredClaims, now: SystemTime) -> Result<(), Error> {
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let leeway = self.leeway.as_secs();
+
+        match claims.expiration {
+            Some(expiration) if now >= expiration.saturating_add(leeway) => {
+                return Err(Error::TokenExpired)
+            }
+            None if self.require_expiration => return Err(Error::NoExpiration),
+            _ => (),
+        }
+        if claims
+            .not_before
+            .map_or(false, |not_before| now.saturating_add(leeway) < not_before)
+        {
+            return Err(Error::TokenNotYetValid);
+        }
+
+        if let Some(ref audiences) = self.audiences {
+            let allowed = claims.audience.as_ref().map_or(false, |audience| {
+                audience
+                    .iter()
+                    .any(|audience| audiences.iter().any(|allowed| allowed == audience))
+            });
+            if !allowed {
+                return Err(Error::AudienceNotAllowed);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Issuer<S> {
+    keys: S,
+    settings: IssuerSettings,
+}
+
+/// Key stores and validation settings by issuer.
+pub struct IssuerStore<S, C = SystemClock> {
+    issuers: BTreeMap<String, Issuer<S>>,
+    clock: C,
+}
+
+impl<S> IssuerStore<S> {
+    pub fn new() -> Self {
+        IssuerStore::with_clock(SystemClock)
+    }
+}
+
+impl<S> Default for IssuerStore<S> {
+    fn default() -> Self {
+        IssuerStore::new()
+    }
+}
+
+impl<S, C: Clock> IssuerStore<S, C> {
+    /// Create a store that validates the time claims with `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        IssuerStore {
+            issuers: BTreeMap::new(),
+            clock,
+        }
+    }
+
+    /// Add or replace the keys and settings of `issuer`, returning the keys
+    /// that were replaced.
+    pub fn insert(
+        &mut self,
+        issuer: impl Into<String>,
+        keys: S,
+        settings: IssuerSettings,
+    ) -> Option<S> {
+        self.issuers
+            .insert(issuer.into(), Issuer { keys, settings })
+            .map(|issuer| issuer.keys)
+    }
+
+    pub fn remove(&mut self, issuer: &str) -> Option<S> {
+        self.issuers.remove(issuer).map(|issuer| issuer.keys)
+    }
+
+    pub fn keys(&self, issuer: &str) -> Option<&S> {
+        self.issuers.get(issuer).map(|issuer| &issuer.keys)
+    }
+
+    pub fn settings(&self, issuer: &str) -> Option<&IssuerSettings> {
+        self.issuers.get(issuer).map(|issuer| &issuer.settings)
+    }
+
+    /// The issuers with keys in the store.
+    pub fn issuers(&self) -> impl Iterator<Item = &str> {
+        self.issuers.keys().map(String::as_str)
+    }
+}
+
+/// Allow objects to be verified with the keys of the issuer they claim.
+pub trait VerifyWithIssuers<T> {
+    fn verify_with_issuers<S, A, C>(self, issuers: &IssuerStore<S, C>) -> Result<T, Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+        C: Clock;
+}
+
+impl<'a, H: JoseHeader, C> VerifyWithIssuers<Token<H, C, Verified>>
+    for Token<H, C, Unverified<'a>>
+{
+    fn verify_with_issuers<S, A, Ck>(
+        self,
+        issuers: &IssuerStore<S, Ck>,
+    ) -> Result<Token<H, C, Verified>, Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+        Ck: Clock,
+    {
+        let header = self.header();
+        let algorithm = header.algorithm_type();
+        if algorithm == AlgorithmType::None {
+            return Err(Error::UnsecuredToken);
+        }
+
+        let claims = RegisteredClaims::from_base64(self.signature.claims_str)?;
+        let issuer_name = claims.issuer.as_deref().ok_or(Error::UnknownIssuer(None))?;
+        let issuer = issuers
+            .issuers
+            .get(issuer_name)
+            .ok_or_else(|| Error::UnknownIssuer(Some(issuer_name.to_owned())))?;
+        let settings = &issuer.settings;
+
+        if let Some(ref algorithms) = settings.algorithms {
+            if !algorithms.contains(&algorithm) {
+                return Err(Error::AlgorithmNotAllowed(algorithm));
+            }
+        }
+
+        let token = match settings.candidate_attempts {
+            Some(attempts) if header.key_id().is_none() => {
+                let candidates = CandidateKeys::new(&issuer.keys).with_max_attempts(attempts);
+                self.verify_with_candidates(&candidates)?.0
+            }
+            _ => self.verify_with_store(&issuer.keys)?,
+        };
+
+        settings.validate(&claims, issuers.clock.now())?;
+        Ok(token)
+    }
+}
+
+impl<H, C> VerifyWithIssuers<Token<H, C, Verified>> for &str
+where
+    H: FromBase64 + JoseHeader,
+    C: FromBase64,
+{
+    fn verify_with_issuers<S, A, Ck>(
+        self,
+        issuers: &IssuerStore<S, Ck>,
+    ) -> Result<Token<H, C, Verified>, Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+        Ck: Clock,
+    {
+        let unverified: Token<H, C, _> = Token::parse_unverified(self)?;
+        unverified.verify_with_issuers(issuers)
+    }
+}
+
+impl<C: FromBase64> VerifyWithIssuers<C> for &str {
+    fn verify_with_issuers<S, A, Ck>(self, issuers: &IssuerStore<S, Ck>) -> Result<C, Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+        Ck: Clock,
+    {
+        let token: Token<Header, C, _> = self.verify_with_issuers(issuers)?;
+        Ok(token.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use hmac::{Hmac, Mac};
+    use sha2::{Sha256, Sha384};
+
+    use crate::algorithm::key_ring::{Clock, ManualClock};
+    use crate::algorithm::AlgorithmType;
+    use crate::claims::{Claims, RegisteredClaims};
+    use crate::error::Error;
+    use crate::issuer::{IssuerSettings, IssuerStore, VerifyWithIssuers};
+    use crate::token::signed::{SignWithKey, SignWithStore};
+
+    type Keys = BTreeMap<&'static str, Hmac<Sha256>>;
+
+    fn keys(secret: &str) -> Result<Keys, Error> {
+        let mut keys = BTreeMap::new();
+        keys.insert("key", Hmac::new_from_slice(secret.as_bytes())?);
+        Ok(keys)
+    }
+
+    fn claims(issuer: &str) -> Claims {
+        Claims::new(RegisteredClaims {
+            issuer: Some(issuer.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn verify<C: Clock>(token: &str, issuers: &IssuerStore<Keys, C>) -> Result<Claims, Error> {
+        token.verify_with_issuers(issuers)
+    }
+
+    #[test]
+    fn issuers_do_not_share_keys() -> Result<(), Error> {
+        let mut issuers = IssuerStore::new();
+        issuers.insert("first", keys("first-secret")?, IssuerSettings::default());
+        issuers.insert("second", keys("second-secret")?, IssuerSettings::default());
+        let mut other_keys = BTreeMap::new();
+        other_keys.insert("other-key", Hmac::new_from_slice(b"other-secret")?);
+        issuers.insert("other", other_keys, IssuerSettings::default());
+
+        let first_keys = issuers.keys("first").unwrap();
+        let token = ("key", claims("first")).sign_with_store(first_keys)?;
+        assert_eq!(verify(&token, &issuers)?, claims("first"));
+
+        // The key of the second issuer has the same id, so the signature is
+        // checked with it and fails, which HMAC reports as a MAC error.
+        let impersonating = ("key", claims("second")).sign_with_store(first_keys)?;
+        match verify(&impersonating, &issuers) {
+            Err(Error::RustCryptoMac(_)) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let impersonating = ("key", claims("other")).sign_with_store(first_keys)?;
+        match verify(&impersonating, &issuers) {
+            Err(Error::NoKeyWithKeyId(key_id)) => assert_eq!(key_id, "key"),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let unknown = ("key", claims("third")).sign_with_store(first_keys)?;
+        match verify(&unknown, &issuers) {
+            Err(Error::UnknownIssuer(Some(issuer))) => assert_eq!(issuer, "third"),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let anonymous = ("key", Claims::default()).sign_with_store(first_keys)?;
+        match verify(&anonymous, &issuers) {
+            Err(Error::UnknownIssuer(None)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tokens_without_key_id() -> Result<(), Error> {
+        let mut issuers = IssuerStore::new();
+        issuers.insert("strict", keys("secret")?, IssuerSettings::default());
+        let lenient = IssuerSettings {
+            candidate_attempts: Some(1),
+            ..Default::default()
+        };
+        issuers.insert("lenient", keys("secret")?, lenient);
+
+        let key = &issuers.keys("lenient").unwrap()["key"];
+        let token = claims("lenient").sign_with_key(key)?;
+        assert_eq!(verify(&token, &issuers)?, claims("lenient"));
+
+        let token = claims("strict").sign_with_key(key)?;
+        match verify(&token, &issuers) {
+            Err(Error::NoKeyId) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn settings_are_per_issuer() -> Result<(), Error> {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let clock = ManualClock::new(UNIX_EPOCH + 100 * day);
+        let mut issuers = IssuerStore::with_clock(&clock);
+        issuers.insert("lenient", keys("secret")?, IssuerSettings::default());
+        let strict = IssuerSettings {
+            algorithms: Some(vec![AlgorithmType::Hs256]),
+            audiences: Some(vec!["gateway".to_owned()]),
+            require_expiration: true,
+            leeway: Duration::from_secs(60),
+            ..Default::default()
+        };
+        issuers.insert("strict", keys("secret")?, strict);
+        let keys = issuers.keys("strict").unwrap();
+
+        let mut strict_claims = claims("strict");
+        let now = 100 * day.as_secs();
+        strict_claims.registered.expiration = Some(now + 30);
+        strict_claims.registered.audience = Some(serde_json::from_str(r#"["other", "gateway"]"#)?);
+        let token = ("key", &strict_claims).sign_with_store(keys)?;
+        assert_eq!(verify(&token, &issuers)?, strict_claims);
+
+        clock.advance(Duration::from_secs(120));
+        assert!(matches!(verify(&token, &issuers), Err(Error::TokenExpired)));
+
+        let lenient = ("key", claims("lenient")).sign_with_store(keys)?;
+        verify(&lenient, &issuers)?;
+        let token = ("key", claims("strict")).sign_with_store(keys)?;
+        assert!(matches!(verify(&token, &issuers), Err(Error::NoExpiration)));
+
+        let mut not_yet_valid = claims("lenient");
+        not_yet_valid.registered.not_before = Some(now + day.as_secs());
+        let token = ("key", not_yet_valid).sign_with_store(keys)?;
+        assert!(matches!(
+            verify(&token, &issuers),
+            Err(Error::TokenNotYetValid)
+        ));
+
+        strict_claims.registered.expiration = Some(now + day.as_secs());
+        strict_claims.registered.audience = None;
+        let token = ("key", &strict_claims).sign_with_store(keys)?;
+        assert!(matches!(
+            verify(&token, &issuers),
+            Err(Error::AudienceNotAllowed)
+        ));
+
+        let hs384: Hmac<Sha384> = Hmac::new_from_slice(b"secret")?;
+        let token = claims("strict").sign_with_key(&hs384)?;
+        match verify(&token, &issuers) {
+            Err(Error::AlgorithmNotAllowed(AlgorithmType::Hs384)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+}
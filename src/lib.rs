@@ -94,26 +94,37 @@ doctest!("../README.md");
 
 use std::borrow::Cow;
 
+use base64::write::EncoderStringWriter;
 #[cfg(doctest)]
 use doc_comment::doctest;
 use serde::{Deserialize, Serialize};
 
+pub use crate::algorithm::generate::GeneratedKey;
 #[cfg(feature = "openssl")]
-pub use crate::algorithm::openssl::PKeyWithDigest;
+pub use crate::algorithm::openssl::{
+    EdDsaPKey, LowSPKeyWithDigest, PKeyWithDigest, PssPKeyWithDigest,
+};
 pub use crate::algorithm::store::Store;
-pub use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+pub use crate::algorithm::{AlgorithmType, KeyPair, SigningAlgorithm, VerifyingAlgorithm};
 pub use crate::claims::Claims;
 pub use crate::claims::RegisteredClaims;
 pub use crate::error::Error;
 pub use crate::header::{Header, JoseHeader};
+pub use crate::jwk::{Jwk, JwkSet, ThumbprintKeyIds, ToJwk};
 pub use crate::token::signed::{SignWithKey, SignWithStore};
-pub use crate::token::verified::{VerifyWithKey, VerifyWithStore};
+pub use crate::token::unsecured::{SignUnsecured, Unsecured, VerifyUnsecured};
+pub use crate::token::verified::{
+    CandidateKeys, VerifyWithCandidates, VerifyWithKey, VerifyWithStore,
+};
 pub use crate::token::{Unsigned, Unverified, Verified};
 
 pub mod algorithm;
 pub mod claims;
 pub mod error;
 pub mod header;
+pub mod issuer;
+pub mod jwk;
+pub mod remote;
 pub mod token;
 
 const SEPARATOR: &str = ".";
@@ -157,15 +168,28 @@ impl<H, C, S> From<Token<H, C, S>> for (H, C) {
 /// [Serialize](../../serde/trait.Serialize.html). as a base64 encoding of
 /// the object's JSON representation.
 pub trait ToBase64 {
-    fn to_base64(&self) -> Result<Cow<str>, Error>;
+    fn to_base64(&self) -> Result<Cow<'_, str>, Error>;
+
+    /// Append the base64 encoding to `buffer`.
+    fn write_base64(&self, buffer: &mut String) -> Result<(), Error> {
+        buffer.push_str(&self.to_base64()?);
+        Ok(())
+    }
 }
 
 impl<T: Serialize> ToBase64 for T {
-    fn to_base64(&self) -> Result<Cow<str>, Error> {
+    fn to_base64(&self) -> Result<Cow<'_, str>, Error> {
         let json_bytes = serde_json::to_vec(&self)?;
         let encoded_json_bytes = base64::encode_config(&json_bytes, base64::URL_SAFE_NO_PAD);
         Ok(Cow::Owned(encoded_json_bytes))
     }
+
+    fn write_base64(&self, buffer: &mut String) -> Result<(), Error> {
+        let mut writer = EncoderStringWriter::from(buffer, base64::URL_SAFE_NO_PAD);
+        serde_json::to_writer(&mut writer, &self)?;
+        writer.into_inner();
+        Ok(())
+    }
 }
 
 /// A trait used to parse objects from base64 encoding. The return type can
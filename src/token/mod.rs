@@ -1,6 +1,7 @@
 //! A structured representation of a JWT.
 
 pub mod signed;
+pub mod unsecured;
 pub mod verified;
 
 pub struct Unsigned;
@@ -0,0 +1,268 @@
+//! Opt-in support for [unsecured JWTs](https://tools.ietf.org/html/rfc7519#section-6),
+//! which use the `none` algorithm and an empty signature. They provide no
+//! integrity protection, so they are only produced and accepted by the
+//! `SignUnsecured` and `VerifyUnsecured` traits, which require an
+//! `Unsecured` value created with `Unsecured::allow_unsecured`. The
+//! `VerifyWithKey` and `VerifyWithStore` traits always reject them.
+//! ## Examples
+//! ```
+//! use jwt::{SignUnsecured, Unsecured, VerifyUnsecured};
+//! use std::collections::BTreeMap;
+//!
+//! # use jwt::Error;
+//! # fn try_main() -> Result<(), Error> {
+//! let unsecured = Unsecured::allow_unsecured();
+//! let mut claims = BTreeMap::new();
+//! claims.insert("sub", "someone");
+//! let token_str = claims.sign_unsecured(&unsecured)?;
+//! assert_eq!(token_str, "eyJhbGciOiJub25lIn0.eyJzdWIiOiJzb21lb25lIn0.");
+//!
+//! let claims: BTreeMap<String, String> = token_str.as_str().verify_unsecured(&unsecured)?;
+//! assert_eq!(claims["sub"], "someone");
+//! # Ok(())
+//! # }
+//! # try_main().unwrap()
+//! ```
+
+use crate::algorithm::AlgorithmType;
+use crate::error::Error;
+use crate::header::{Header, JoseHeader};
+use crate::token::{Signed, Unsigned, Unverified, Verified};
+use crate::{FromBase64, ToBase64, Token, SEPARATOR};
+
+/// Permission to sign and verify unsecured tokens.
+#[derive(Debug)]
+pub struct Unsecured {
+    _private: (),
+}
+
+impl Unsecured {
+    /// Explicitly allow unsecured tokens. Only use this where the integrity
+    /// of the token is guaranteed by other means, such as a trusted channel.
+    pub fn allow_unsecured() -> Self {
+        Unsecured { _private: () }
+    }
+}
+
+/// Allow objects to be encoded as unsecured tokens.
+pub trait SignUnsecured<T> {
+    fn sign_unsecured(self, unsecured: &Unsecured) -> Result<T, Error>;
+}
+
+/// Allow unsecured tokens to be accepted.
+pub trait VerifyUnsecured<T> {
+    fn verify_unsecured(self, unsecured: &Unsecured) -> Result<T, Error>;
+}
+
+impl<C: ToBase64> SignUnsecured<String> for C {
+    fn sign_unsecured(self, unsecured: &Unsecured) -> Result<String, Error> {
+        let header = Header {
+            algorithm: AlgorithmType::None,
+            ..Default::default()
+        };
+
+        let token = Token::new(header, self).sign_unsecured(unsecured)?;
+        Ok(token.signature.token_string)
+    }
+}
+
+impl<H, C> SignUnsecured<Token<H, C, Signed>> for Token<H, C, Unsigned>
+where
+    H: ToBase64 + JoseHeader,
+    C: ToBase64,
+{
+    fn sign_unsecured(self, _unsecured: &Unsecured) -> Result<Token<H, C, Signed>, Error> {
+        let header_algorithm = self.header.algorithm_type();
+        if header_algorithm != AlgorithmType::None {
+            return Err(Error::AlgorithmMismatch(
+                header_algorithm,
+                AlgorithmType::None,
+            ));
+        }
+
+        let header = self.header.to_base64()?;
+        let claims = self.claims.to_base64()?;
+
+        let token_string = [&*header, &*claims, ""].join(SEPARATOR);
+
+        Ok(Token {
+            header: self.header,
+            claims: self.claims,
+            signature: Signed { token_string },
+        })
+    }
+}
+
+impl<'a, H: JoseHeader, C> VerifyUnsecured<Token<H, C, Verified>> for Token<H, C, Unverified<'a>> {
+    fn verify_unsecured(self, _unsecured: &Unsecured) -> Result<Token<H, C, Verified>, Error> {
+        let header_algorithm = self.header.algorithm_type();
+        if header_algorithm != AlgorithmType::None {
+            return Err(Error::AlgorithmMismatch(
+                header_algorithm,
+                AlgorithmType::None,
+            ));
+        }
+
+        if !self.signature.signature_str.is_empty() {
+            return Err(Error::InvalidSignature);
+        }
+
+        Ok(Token {
+            header: self.header,
+            claims: self.claims,
+            signature: Verified,
+        })
+    }
+}
+
+impl<H, C> VerifyUnsecured<Token<H, C, Verified>> for &str
+where
+    H: FromBase64 + JoseHeader,
+    C: FromBase64,
+{
+    fn verify_unsecured(self, unsecured: &Unsecured) -> Result<Token<H, C, Verified>, Error> {
+        let unverified = Token::parse_unverified(self)?;
+        unverified.verify_unsecured(unsecured)
+    }
+}
+
+impl<C: FromBase64> VerifyUnsecured<C> for &str {
+    fn verify_unsecured(self, unsecured: &Unsecured) -> Result<C, Error> {
+        let token: Token<Header, C, _> = self.verify_unsecured(unsecured)?;
+        Ok(token.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use crate::algorithm::{AlgorithmType, SigningAlgorithm, VerifyingAlgorithm};
+    use crate::error::Error;
+    use crate::header::Header;
+    use crate::token::signed::SignWithKey;
+    use crate::token::unsecured::{SignUnsecured, Unsecured, VerifyUnsecured};
+    use crate::token::verified::{VerifyWithKey, VerifyWithStore};
+    use crate::Token;
+
+    // Header   {"alg":"none"}
+    // Claims   {"sub":"someone"}
+    const UNSECURED_TOKEN: &str = "eyJhbGciOiJub25lIn0.eyJzdWIiOiJzb21lb25lIn0.";
+
+    /// A key that claims to implement the `none` algorithm.
+    struct NoneKey;
+
+    impl SigningAlgorithm for NoneKey {
+        fn algorithm_type(&self) -> AlgorithmType {
+            AlgorithmType::None
+        }
+
+        fn sign(&self, _header: &str, _claims: &str) -> Result<String, Error> {
+            Ok(String::new())
+        }
+    }
+
+    impl VerifyingAlgorithm for NoneKey {
+        fn algorithm_type(&self) -> AlgorithmType {
+            AlgorithmType::None
+        }
+
+        fn verify_bytes(&self, _: &str, _: &str, _: &[u8]) -> Result<bool, Error> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    pub fn roundtrip() -> Result<(), Error> {
+        let unsecured = Unsecured::allow_unsecured();
+        let header = Header {
+            algorithm: AlgorithmType::None,
+            key_id: Some("trusted".to_owned()),
+            ..Default::default()
+        };
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+
+        let token = Token::new(header, claims).sign_unsecured(&unsecured)?;
+        assert!(token.as_str().ends_with('.'));
+
+        let verified: Token<Header, BTreeMap<String, String>, _> =
+            token.as_str().verify_unsecured(&unsecured)?;
+        assert_eq!(verified.header().key_id.as_deref(), Some("trusted"));
+        assert_eq!(verified.claims()["sub"], "someone");
+        Ok(())
+    }
+
+    #[test]
+    pub fn secured_header_is_rejected() -> Result<(), Error> {
+        let unsecured = Unsecured::allow_unsecured();
+        let token: Token<Header, BTreeMap<String, String>, _> = Default::default();
+        match token.sign_unsecured(&unsecured) {
+            Err(Error::AlgorithmMismatch(AlgorithmType::Hs256, AlgorithmType::None)) => (),
+            other => panic!("Wrong result: {:?}", other.map(|_| ())),
+        }
+
+        let key: Hmac<Sha256> = Hmac::new_from_slice(b"secret")?;
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        let token_str = claims.sign_with_key(&key)?;
+        let result: Result<BTreeMap<String, String>, _> =
+            token_str.as_str().verify_unsecured(&unsecured);
+        match result {
+            Err(Error::AlgorithmMismatch(AlgorithmType::Hs256, AlgorithmType::None)) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn signature_must_be_empty() {
+        let unsecured = Unsecured::allow_unsecured();
+        let token_str = format!("{}c2lnbmF0dXJl", UNSECURED_TOKEN);
+        let result: Result<BTreeMap<String, String>, _> =
+            token_str.as_str().verify_unsecured(&unsecured);
+        match result {
+            Err(Error::InvalidSignature) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn not_accepted_by_keys() -> Result<(), Error> {
+        let key: Hmac<Sha256> = Hmac::new_from_slice(b"secret")?;
+        let result: Result<BTreeMap<String, String>, _> = UNSECURED_TOKEN.verify_with_key(&key);
+        match result {
+            Err(Error::UnsecuredToken) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let result: Result<BTreeMap<String, String>, _> = UNSECURED_TOKEN.verify_with_key(&NoneKey);
+        match result {
+            Err(Error::UnsecuredToken) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let mut store = BTreeMap::new();
+        store.insert("trusted", NoneKey);
+        // Header   {"alg":"none","kid":"trusted"}
+        // Claims   {"sub":"someone"}
+        let with_key_id = "eyJhbGciOiJub25lIiwia2lkIjoidHJ1c3RlZCJ9.eyJzdWIiOiJzb21lb25lIn0.";
+        let result: Result<BTreeMap<String, String>, _> = with_key_id.verify_with_store(&store);
+        match result {
+            Err(Error::UnsecuredToken) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn not_signed_by_keys() {
+        let mut claims = BTreeMap::new();
+        claims.insert("sub", "someone");
+        match claims.sign_with_key(&NoneKey) {
+            Err(Error::UnsecuredToken) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+}
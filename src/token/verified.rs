@@ -1,7 +1,8 @@
 use crate::algorithm::store::Store;
-use crate::algorithm::VerifyingAlgorithm;
+use crate::algorithm::{AlgorithmType, VerifyingAlgorithm};
 use crate::error::Error;
 use crate::header::{Header, JoseHeader};
+use crate::jwk::KeyOperation;
 use crate::token::{Unverified, Verified};
 use crate::{FromBase64, Token, SEPARATOR};
 
@@ -18,6 +19,61 @@ pub trait VerifyWithStore<T> {
         A: VerifyingAlgorithm;
 }
 
+/// Allow objects to be verified with the candidate keys of a store, for
+/// tokens signed without a `kid`. The result includes the id of the key that
+/// verified the token, or `None` if the key has no id.
+pub trait VerifyWithCandidates<T> {
+    fn verify_with_candidates<S, A>(
+        self,
+        candidates: &CandidateKeys<'_, S>,
+    ) -> Result<(T, Option<String>), Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm;
+}
+
+/// The keys of a store to try for a token without a `kid`: every key listed
+/// by `Store::candidates` with the algorithm of the token header, optionally
+/// narrowed down with a filter. Tokens with a `kid` are verified with the key
+/// of that id, as with `VerifyWithStore`.
+///
+/// Every attempt costs a signature verification, so at most `max_attempts`
+/// keys are tried before failing with `Error::CandidateLimitExceeded`.
+pub struct CandidateKeys<'a, S: Store> {
+    store: &'a S,
+    filter: Option<Box<CandidateFilter<'a, S::Algorithm>>>,
+    max_attempts: usize,
+}
+
+type CandidateFilter<'a, A> = dyn Fn(Option<&str>, &A) -> bool + 'a;
+
+impl<'a, S: Store> CandidateKeys<'a, S> {
+    pub const DEFAULT_MAX_ATTEMPTS: usize = 10;
+
+    pub fn new(store: &'a S) -> Self {
+        CandidateKeys {
+            store,
+            filter: None,
+            max_attempts: Self::DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Only try the keys for which `filter` returns true, given the key id
+    /// and the key.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(Option<&str>, &S::Algorithm) -> bool + 'a,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+}
+
 impl<'a, H: JoseHeader, C> VerifyWithKey<Token<H, C, Verified>> for Token<H, C, Unverified<'a>> {
     fn verify_with_key(
         self,
@@ -25,26 +81,21 @@ impl<'a, H: JoseHeader, C> VerifyWithKey<Token<H, C, Verified>> for Token<H, C,
     ) -> Result<Token<H, C, Verified>, Error> {
         let header = self.header();
         let header_algorithm = header.algorithm_type();
+        if header_algorithm == AlgorithmType::None {
+            return Err(Error::UnsecuredToken);
+        }
+
         let key_algorithm = key.algorithm_type();
         if header_algorithm != key_algorithm {
             return Err(Error::AlgorithmMismatch(header_algorithm, key_algorithm));
         }
 
-        let Unverified {
-            header_str,
-            claims_str,
-            signature_str,
-        } = self.signature;
-
-        if key.verify(header_str, claims_str, signature_str)? {
-            Ok(Token {
-                header: self.header,
-                claims: self.claims,
-                signature: Verified,
-            })
-        } else {
-            Err(Error::InvalidSignature)
-        }
+        verify_signature(key, &key_algorithm, &self.signature)?;
+        Ok(Token {
+            header: self.header,
+            claims: self.claims,
+            signature: Verified,
+        })
     }
 }
 
@@ -55,16 +106,102 @@ impl<'a, H: JoseHeader, C> VerifyWithStore<Token<H, C, Verified>> for Token<H, C
         A: VerifyingAlgorithm,
     {
         let header = self.header();
+        if header.algorithm_type() == AlgorithmType::None {
+            return Err(Error::UnsecuredToken);
+        }
+
         let key_id = header.key_id().ok_or(Error::NoKeyId)?;
         let key = store
-            .get(key_id)
+            .get_with_algorithm(key_id, &header.algorithm_type())
             .ok_or_else(|| Error::NoKeyWithKeyId(key_id.to_owned()))?;
 
         self.verify_with_key(key)
     }
 }
 
-impl<'a, H, C> VerifyWithKey<Token<H, C, Verified>> for &'a str
+impl<'a, H: JoseHeader, C> VerifyWithCandidates<Token<H, C, Verified>>
+    for Token<H, C, Unverified<'a>>
+{
+    /// Fails with `Error::NoCandidateKeys` if no key has the algorithm of the
+    /// token, and with `Error::InvalidSignature` if none of the keys tried
+    /// verifies it, whatever the reason for each key.
+    fn verify_with_candidates<S, A>(
+        self,
+        candidates: &CandidateKeys<'_, S>,
+    ) -> Result<(Token<H, C, Verified>, Option<String>), Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+    {
+        let header = self.header();
+        let header_algorithm = header.algorithm_type();
+        if header_algorithm == AlgorithmType::None {
+            return Err(Error::UnsecuredToken);
+        }
+
+        if let Some(key_id) = header.key_id() {
+            let key_id = key_id.to_owned();
+            let token = self.verify_with_store(candidates.store)?;
+            return Ok((token, Some(key_id)));
+        }
+
+        let mut attempts = 0;
+        for (key_id, key) in candidates.store.candidates() {
+            if key.algorithm_type() != header_algorithm {
+                continue;
+            }
+            if let Some(ref filter) = candidates.filter {
+                if !filter(key_id, key) {
+                    continue;
+                }
+            }
+            if attempts == candidates.max_attempts {
+                return Err(Error::CandidateLimitExceeded(attempts));
+            }
+            attempts += 1;
+
+            if verify_signature(key, &header_algorithm, &self.signature).is_ok() {
+                let token = Token {
+                    header: self.header,
+                    claims: self.claims,
+                    signature: Verified,
+                };
+                return Ok((token, key_id.map(str::to_owned)));
+            }
+        }
+
+        if attempts == 0 {
+            Err(Error::NoCandidateKeys)
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
+}
+
+/// Check the restrictions of `key` and the signature of a token.
+fn verify_signature<A: VerifyingAlgorithm + ?Sized>(
+    key: &A,
+    algorithm: &AlgorithmType,
+    signature: &Unverified,
+) -> Result<(), Error> {
+    let Unverified {
+        header_str,
+        claims_str,
+        signature_str,
+    } = *signature;
+
+    if let Some(metadata) = key.key_metadata() {
+        metadata.check(KeyOperation::Verify, algorithm, claims_str)?;
+    }
+
+    if key.verify(header_str, claims_str, signature_str)? {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+impl<H, C> VerifyWithKey<Token<H, C, Verified>> for &str
 where
     H: FromBase64 + JoseHeader,
     C: FromBase64,
@@ -78,7 +215,7 @@ where
     }
 }
 
-impl<'a, H, C> VerifyWithStore<Token<H, C, Verified>> for &'a str
+impl<H, C> VerifyWithStore<Token<H, C, Verified>> for &str
 where
     H: FromBase64 + JoseHeader,
     C: FromBase64,
@@ -93,14 +230,32 @@ where
     }
 }
 
-impl<'a, C: FromBase64> VerifyWithKey<C> for &'a str {
+impl<H, C> VerifyWithCandidates<Token<H, C, Verified>> for &str
+where
+    H: FromBase64 + JoseHeader,
+    C: FromBase64,
+{
+    fn verify_with_candidates<S, A>(
+        self,
+        candidates: &CandidateKeys<'_, S>,
+    ) -> Result<(Token<H, C, Verified>, Option<String>), Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+    {
+        let unverified: Token<H, C, _> = Token::parse_unverified(self)?;
+        unverified.verify_with_candidates(candidates)
+    }
+}
+
+impl<C: FromBase64> VerifyWithKey<C> for &str {
     fn verify_with_key(self, key: &impl VerifyingAlgorithm) -> Result<C, Error> {
         let token: Token<Header, C, _> = self.verify_with_key(key)?;
         Ok(token.claims)
     }
 }
 
-impl<'a, C: FromBase64> VerifyWithStore<C> for &'a str {
+impl<C: FromBase64> VerifyWithStore<C> for &str {
     fn verify_with_store<S, A>(self, store: &S) -> Result<C, Error>
     where
         S: Store<Algorithm = A>,
@@ -111,9 +266,23 @@ impl<'a, C: FromBase64> VerifyWithStore<C> for &'a str {
     }
 }
 
+impl<C: FromBase64> VerifyWithCandidates<C> for &str {
+    fn verify_with_candidates<S, A>(
+        self,
+        candidates: &CandidateKeys<'_, S>,
+    ) -> Result<(C, Option<String>), Error>
+    where
+        S: Store<Algorithm = A>,
+        A: VerifyingAlgorithm,
+    {
+        let (token, key_id): (Token<Header, C, _>, _) = self.verify_with_candidates(candidates)?;
+        Ok((token.claims, key_id))
+    }
+}
+
 impl<'a, H: FromBase64, C: FromBase64> Token<H, C, Unverified<'a>> {
     /// Not recommended. Parse the header and claims without checking the validity of the signature.
-    pub fn parse_unverified(token_str: &str) -> Result<Token<H, C, Unverified>, Error> {
+    pub fn parse_unverified(token_str: &str) -> Result<Token<H, C, Unverified<'_>>, Error> {
         let [header_str, claims_str, signature_str] = split_components(token_str)?;
         let header = H::from_base64(header_str)?;
         let claims = C::from_base64(claims_str)?;
@@ -155,7 +324,9 @@ mod tests {
 
     use crate::algorithm::VerifyingAlgorithm;
     use crate::error::Error;
-    use crate::token::verified::{VerifyWithKey, VerifyWithStore};
+    use crate::token::verified::{
+        CandidateKeys, VerifyWithCandidates, VerifyWithKey, VerifyWithStore,
+    };
 
     #[derive(Debug, Deserialize)]
     struct Claims {
@@ -286,4 +457,60 @@ mod tests {
 
         Ok(())
     }
+
+    fn hs256_store() -> Result<BTreeMap<&'static str, Hmac<Sha256>>, Error> {
+        let mut store = BTreeMap::new();
+        for key_id in ["a", "b", "c"] {
+            store.insert(key_id, Hmac::new_from_slice(key_id.as_bytes())?);
+        }
+        Ok(store)
+    }
+
+    #[test]
+    pub fn verify_claims_with_candidates() -> Result<(), Error> {
+        use crate::token::signed::SignWithKey;
+
+        let store = hs256_store()?;
+        let mut claims = BTreeMap::new();
+        claims.insert("name", "Jane Doe");
+        let token = claims.sign_with_key(&store["c"])?;
+
+        let (claims, key_id): (Claims, _) =
+            token.verify_with_candidates(&CandidateKeys::new(&store))?;
+        assert_eq!(claims.name, "Jane Doe");
+        assert_eq!(key_id.as_deref(), Some("c"));
+
+        let first_only = CandidateKeys::new(&store).with_max_attempts(1);
+        match token.verify_with_candidates(&first_only) as Result<(Claims, _), _> {
+            Err(Error::CandidateLimitExceeded(1)) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let filtered = CandidateKeys::new(&store).with_filter(|key_id, _| key_id != Some("c"));
+        match token.verify_with_candidates(&filtered) as Result<(Claims, _), _> {
+            Err(Error::InvalidSignature) => (),
+            other => panic!("Wrong result: {:?}", other),
+        }
+
+        let hs512_store: BTreeMap<_, _> = create_test_data()?;
+        let other_algorithm =
+            CandidateKeys::new(&hs512_store).with_filter(|key_id, _| key_id == Some("second_key"));
+        match token.verify_with_candidates(&other_algorithm) as Result<(Claims, _), _> {
+            Err(Error::NoCandidateKeys) => Ok(()),
+            other => panic!("Wrong result: {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn verify_claims_with_candidates_and_key_id() -> Result<(), Error> {
+        let key_store: BTreeMap<_, _> = create_test_data()?;
+        let candidates = CandidateKeys::new(&key_store).with_max_attempts(0);
+
+        let (claims, key_id): (Claims, _) =
+            JANE_DOE_SECOND_KEY_TOKEN.verify_with_candidates(&candidates)?;
+
+        assert_eq!(claims.name, "Jane Doe");
+        assert_eq!(key_id.as_deref(), Some("second_key"));
+        Ok(())
+    }
 }